@@ -0,0 +1,161 @@
+use crate::dto::{MemberResponse, NetworkIPAssignmentPool};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+/// Addresses assigned to any of the members.
+pub fn used_ips<'a>(members: impl IntoIterator<Item = &'a MemberResponse>) -> HashSet<IpAddr> {
+    members
+        .into_iter()
+        .flat_map(|x| &x.ip_assignments)
+        .filter_map(|x| x.parse().ok())
+        .collect()
+}
+
+/// The controller never hands out IPv4 addresses ending in `.0` or `.255`.
+fn is_assignable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => !matches!(ip.octets()[3], 0 | 255),
+        IpAddr::V6(_) => true,
+    }
+}
+
+/// Address to add to `member` next to the `requested` ones.
+///
+/// Addresses the member already has are free to be handed out again.
+pub fn next_free_for(
+    pools: &[NetworkIPAssignmentPool],
+    members: &[MemberResponse],
+    member: &str,
+    requested: &[IpAddr],
+) -> Option<IpAddr> {
+    let mut used = used_ips(members.iter().filter(|x| x.id != member));
+    used.extend(requested);
+    next_free(pools, &used)
+}
+
+/// First address of the pools, in order, that is not in `used`.
+pub fn next_free(pools: &[NetworkIPAssignmentPool], used: &HashSet<IpAddr>) -> Option<IpAddr> {
+    pools
+        .iter()
+        .filter_map(|pool| pool.range().ok())
+        .find_map(|range| {
+            let mut candidates: Box<dyn Iterator<Item = IpAddr>> = match range {
+                (IpAddr::V4(start), IpAddr::V4(end)) => Box::new(
+                    (u32::from(start)..=u32::from(end)).map(|x| IpAddr::from(x.to_be_bytes())),
+                ),
+                (IpAddr::V6(start), IpAddr::V6(end)) => Box::new(
+                    (u128::from(start)..=u128::from(end)).map(|x| IpAddr::from(x.to_be_bytes())),
+                ),
+                _ => Box::new(std::iter::empty()),
+            };
+            candidates.find(|ip| is_assignable(ip) && !used.contains(ip))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::tests::member;
+
+    fn pools(pools: &[&str]) -> Vec<NetworkIPAssignmentPool> {
+        pools.iter().map(|x| x.parse().unwrap()).collect()
+    }
+
+    fn ips(ips: &[&str]) -> HashSet<IpAddr> {
+        ips.iter().map(|x| x.parse().unwrap()).collect()
+    }
+
+    fn ip(ip: &str) -> Option<IpAddr> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn skips_network_and_broadcast_addresses() {
+        let pools = pools(&["10.0.0.0-10.0.1.255"]);
+        assert_eq!(next_free(&pools, &HashSet::new()), ip("10.0.0.1"));
+        assert_eq!(
+            next_free(&pools, &ips(&["10.0.0.1", "10.0.0.2"])),
+            ip("10.0.0.3")
+        );
+
+        let mut used = (1..=254)
+            .map(|x| format!("10.0.0.{}", x))
+            .collect::<Vec<_>>();
+        used.push("10.0.1.0".to_string());
+        let used = used.iter().map(|x| x.parse().unwrap()).collect();
+        assert_eq!(next_free(&pools, &used), ip("10.0.1.1"));
+    }
+
+    #[test]
+    fn takes_pools_in_order() {
+        let pools = pools(&["10.0.2.1-10.0.2.2", "10.0.1.1-10.0.1.254"]);
+        assert_eq!(next_free(&pools, &ips(&["10.0.2.1"])), ip("10.0.2.2"));
+        assert_eq!(
+            next_free(&pools, &ips(&["10.0.2.1", "10.0.2.2"])),
+            ip("10.0.1.1")
+        );
+
+        let full = ips(&["10.0.2.1", "10.0.2.2", "10.0.1.1"]);
+        assert_eq!(next_free(&pools[..1], &full), None);
+    }
+
+    #[test]
+    fn allocates_from_ipv6_ranges() {
+        let pools = pools(&["fd00::ff-fd00::101"]);
+        assert_eq!(next_free(&pools, &HashSet::new()), ip("fd00::ff"));
+        assert_eq!(
+            next_free(&pools, &ips(&["fd00::ff", "fd00::100"])),
+            ip("fd00::101")
+        );
+    }
+
+    #[test]
+    fn leaves_out_invalid_and_reversed_pools() {
+        let pools = vec![
+            NetworkIPAssignmentPool {
+                ip_range_start: "10.0.0.1".to_string(),
+                ip_range_end: "not an address".to_string(),
+            },
+            NetworkIPAssignmentPool {
+                ip_range_start: "10.0.1.9".to_string(),
+                ip_range_end: "10.0.1.1".to_string(),
+            },
+            NetworkIPAssignmentPool {
+                ip_range_start: "10.0.2.1".to_string(),
+                ip_range_end: "fd00::1".to_string(),
+            },
+            NetworkIPAssignmentPool {
+                ip_range_start: "10.0.3.1".to_string(),
+                ip_range_end: "10.0.3.1".to_string(),
+            },
+        ];
+        assert_eq!(next_free(&pools, &HashSet::new()), ip("10.0.3.1"));
+    }
+
+    #[test]
+    fn reuses_own_addresses_and_skips_requested_ones() {
+        let pools = pools(&["10.0.0.1-10.0.0.254"]);
+        let members = vec![
+            member("1111111111", &["10.0.0.1"]),
+            member("2222222222", &["10.0.0.2", "fd00::1"]),
+        ];
+
+        assert_eq!(
+            next_free_for(&pools, &members, "3333333333", &[]),
+            ip("10.0.0.3")
+        );
+        assert_eq!(
+            next_free_for(&pools, &members, "2222222222", &[]),
+            ip("10.0.0.2")
+        );
+        assert_eq!(
+            next_free_for(
+                &pools,
+                &members,
+                "2222222222",
+                &["10.0.0.2".parse().unwrap()]
+            ),
+            ip("10.0.0.3")
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use crate::dto::{
-    MemberResponse, NetworkIPAssignmentPool, NetworkResponse, NetworkRoute, NetworkV4AssignMode,
-    StatusResponse,
+    MemberResponse, MemberTag, NetworkCapability, NetworkDNS, NetworkIPAssignmentPool,
+    NetworkResponse, NetworkRoute, NetworkTag, NetworkV4AssignMode, NetworkV6AssignMode,
+    PeerResponse, StatusResponse,
 };
-use reqwest::{Client, Error, Response, Url, header};
+use crate::error::Error;
+use futures::future::try_join_all;
+use reqwest::{Client, Response, StatusCode, Url, header};
 use serde::Serialize;
-use serde_json::json;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
 use std::collections::HashMap;
 
 #[derive(Clone)]
@@ -19,17 +23,80 @@ pub struct EditMember {
     #[serde(rename = "ipAssignments")]
     pub ip_assignments: Vec<String>,
     pub name: Option<String>,
+    pub tags: Vec<MemberTag>,
+    pub capabilities: Vec<u32>,
+    #[serde(rename = "activeBridge")]
+    pub active_bridge: bool,
+    #[serde(rename = "noAutoAssignIps")]
+    pub no_auto_assign_ips: bool,
+    #[serde(rename = "ssoExempt")]
+    pub sso_exempt: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct EditNetwork {
     pub name: Option<String>,
     pub private: bool,
+    #[serde(rename = "enableBroadcast")]
+    pub enable_broadcast: bool,
+    pub mtu: u32,
+    #[serde(rename = "multicastLimit")]
+    pub multicast_limit: u32,
+    #[serde(rename = "remoteTraceTarget")]
+    pub remote_trace_target: Option<String>,
+    #[serde(rename = "remoteTraceLevel")]
+    pub remote_trace_level: u32,
     #[serde(rename = "ipAssignmentPools")]
     pub ip_assignment_pools: Vec<NetworkIPAssignmentPool>,
     pub routes: Vec<NetworkRoute>,
     #[serde(rename = "v4AssignMode")]
     pub v4_assign_mode: NetworkV4AssignMode,
+    #[serde(rename = "v6AssignMode")]
+    pub v6_assign_mode: NetworkV6AssignMode,
+    pub dns: NetworkDNS,
+    pub rules: Vec<Value>,
+    pub capabilities: Vec<NetworkCapability>,
+    pub tags: Vec<NetworkTag>,
+    #[serde(rename = "rulesSource")]
+    pub rules_source: String,
+}
+
+impl From<&MemberResponse> for EditMember {
+    fn from(member: &MemberResponse) -> Self {
+        Self {
+            authorized: member.authorized,
+            ip_assignments: member.ip_assignments.clone(),
+            name: member.name.clone(),
+            tags: member.tags.clone(),
+            capabilities: member.capabilities.clone(),
+            active_bridge: member.active_bridge,
+            no_auto_assign_ips: member.no_auto_assign_ips,
+            sso_exempt: member.sso_exempt,
+        }
+    }
+}
+
+impl From<&NetworkResponse> for EditNetwork {
+    fn from(network: &NetworkResponse) -> Self {
+        Self {
+            name: network.name.clone(),
+            private: network.private,
+            enable_broadcast: network.enable_broadcast,
+            mtu: network.mtu,
+            multicast_limit: network.multicast_limit,
+            remote_trace_target: network.remote_trace_target.clone(),
+            remote_trace_level: network.remote_trace_level,
+            ip_assignment_pools: network.ip_assignment_pools.clone(),
+            routes: network.routes.clone(),
+            v4_assign_mode: network.v4_assign_mode.clone(),
+            v6_assign_mode: network.v6_assign_mode.clone(),
+            dns: network.dns.clone(),
+            rules: network.rules.clone(),
+            capabilities: network.capabilities.clone(),
+            tags: network.tags.clone(),
+            rules_source: network.rules_source.clone(),
+        }
+    }
 }
 
 impl APIClient {
@@ -39,7 +106,7 @@ impl APIClient {
         const PKG_REPO: &str = env!("CARGO_PKG_REPOSITORY");
 
         let mut headers = header::HeaderMap::new();
-        headers.insert("X-ZT1-AUTH", header::HeaderValue::from_str(token).unwrap());
+        headers.insert("X-ZT1-AUTH", header::HeaderValue::from_str(token)?);
 
         let base_url = Url::parse(url)?;
 
@@ -53,26 +120,50 @@ impl APIClient {
         })
     }
 
+    /// Maps non-success statuses to the matching [`Error`].
+    async fn check(response: Response) -> Result<Response, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Error::Unauthorized),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(response.url().path().to_string())),
+            _ => Err(Error::Controller {
+                status,
+                body: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+        let body = Self::check(response).await?.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
     pub async fn status(&self) -> Result<StatusResponse, Error> {
         let url = self.base_url.join("status").unwrap();
 
-        self.client
-            .get(url)
-            .send()
-            .await?
-            .json::<StatusResponse>()
-            .await
+        Self::decode(self.client.get(url).send().await?).await
+    }
+
+    /// Peers of the local service, only available when the controller node runs it.
+    pub async fn peers(&self) -> Result<Vec<PeerResponse>, Error> {
+        let url = self.base_url.join("peer").unwrap();
+
+        Self::decode(self.client.get(url).send().await?).await
     }
 
     pub async fn networks(&self) -> Result<Vec<String>, Error> {
         let url = self.base_url.join("controller/network").unwrap();
 
-        self.client
-            .get(url)
-            .send()
-            .await?
-            .json::<Vec<String>>()
-            .await
+        Self::decode(self.client.get(url).send().await?).await
+    }
+
+    pub async fn networks_detailed(&self) -> Result<Vec<NetworkResponse>, Error> {
+        let ids = self.networks().await?;
+        try_join_all(ids.iter().map(|id| self.network(id))).await
     }
 
     pub async fn network(&self, id: &str) -> Result<NetworkResponse, Error> {
@@ -81,35 +172,41 @@ impl APIClient {
             .join(&format!("controller/network/{}", id))
             .unwrap();
 
-        self.client
+        let response = self
+            .client
             .get(url)
             .body(json!({}).to_string())
             .send()
-            .await?
-            .json::<NetworkResponse>()
-            .await
+            .await?;
+
+        Self::decode(response).await
     }
 
-    pub async fn edit_network(&self, id: &str, data: EditNetwork) -> Result<Response, Error> {
+    pub async fn edit_network(&self, id: &str, data: EditNetwork) -> Result<(), Error> {
         let url = self
             .base_url
             .join(&format!("controller/network/{}", id))
             .unwrap();
 
-        self.client
+        let response = self
+            .client
             .post(url)
             .body(json!(&data).to_string())
             .send()
-            .await
+            .await?;
+
+        Self::check(response).await.map(|_| ())
     }
 
-    pub async fn delete_network(&self, id: &str) -> Result<Response, Error> {
+    pub async fn delete_network(&self, id: &str) -> Result<(), Error> {
         let url = self
             .base_url
             .join(&format!("controller/network/{}", id))
             .unwrap();
 
-        self.client.delete(url).send().await
+        Self::check(self.client.delete(url).send().await?)
+            .await
+            .map(|_| ())
     }
 
     pub async fn create_network(&self, node_id: &str) -> Result<NetworkResponse, Error> {
@@ -118,27 +215,25 @@ impl APIClient {
             .join(&format!("controller/network/{}______", node_id))
             .unwrap();
 
-        self.client
-            .post(url)
-            .body("{}")
-            .send()
-            .await?
-            .json::<NetworkResponse>()
-            .await
+        let response = self.client.post(url).body("{}").send().await?;
+
+        Self::decode(response).await
     }
 
-    pub async fn members(&self, nwid: &str) -> Result<HashMap<String, u8>, Error> {
+    pub async fn members(&self, nwid: &str) -> Result<HashMap<String, u64>, Error> {
         let url = self
             .base_url
             .join(&format!("controller/network/{}/member", nwid))
             .unwrap();
 
-        self.client
-            .get(url)
-            .send()
-            .await?
-            .json::<HashMap<String, u8>>()
-            .await
+        Self::decode(self.client.get(url).send().await?).await
+    }
+
+    pub async fn members_detailed(&self, nwid: &str) -> Result<Vec<MemberResponse>, Error> {
+        let ids = self.members(nwid).await?;
+        let mut members = try_join_all(ids.keys().map(|id| self.member(nwid, id))).await?;
+        members.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(members)
     }
 
     pub async fn member(&self, nwid: &str, id: &str) -> Result<MemberResponse, Error> {
@@ -146,38 +241,34 @@ impl APIClient {
             .base_url
             .join(&format!("controller/network/{}/member/{}", nwid, id))
             .unwrap();
-        self.client
-            .get(url)
-            .send()
-            .await?
-            .json::<MemberResponse>()
-            .await
+
+        Self::decode(self.client.get(url).send().await?).await
     }
 
-    pub async fn edit_member(
-        &self,
-        nwid: &str,
-        id: &str,
-        data: EditMember,
-    ) -> Result<Response, Error> {
+    pub async fn edit_member(&self, nwid: &str, id: &str, data: EditMember) -> Result<(), Error> {
         let url = self
             .base_url
             .join(&format!("controller/network/{}/member/{}", nwid, id))
             .unwrap();
 
-        self.client
+        let response = self
+            .client
             .post(url)
             .body(json!(&data).to_string())
             .send()
-            .await
+            .await?;
+
+        Self::check(response).await.map(|_| ())
     }
 
-    pub async fn delete_member(&self, nwid: &str, id: &str) -> Result<Response, Error> {
+    pub async fn delete_member(&self, nwid: &str, id: &str) -> Result<(), Error> {
         let url = self
             .base_url
             .join(&format!("controller/network/{}/member/{}", nwid, id))
             .unwrap();
 
-        self.client.delete(url).send().await
+        Self::check(self.client.delete(url).send().await?)
+            .await
+            .map(|_| ())
     }
 }
@@ -0,0 +1,157 @@
+use crate::api::{APIClient, EditMember, EditNetwork};
+use crate::dto::{MemberResponse, NetworkResponse};
+use crate::error::Error;
+use chrono::Utc;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Version of the archive layout, bumped on incompatible changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Snapshot of every network and member of a controller.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub format_version: u32,
+    /// Node ID of the controller the backup was taken from
+    pub controller: String,
+    /// Unix time in milliseconds, like the controller timestamps
+    pub created: i64,
+    pub tool_version: String,
+    pub networks: Vec<NetworkBackup>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NetworkBackup {
+    pub network: NetworkResponse,
+    pub members: Vec<MemberResponse>,
+}
+
+impl Backup {
+    pub async fn fetch(client: &APIClient) -> Result<Self, Error> {
+        let status = client.status().await?;
+        let networks = client.networks_detailed().await?;
+        let members = try_join_all(networks.iter().map(|x| client.members_detailed(&x.id))).await?;
+
+        Ok(Self {
+            format_version: FORMAT_VERSION,
+            controller: status.address,
+            created: Utc::now().timestamp_millis(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            networks: networks
+                .into_iter()
+                .zip(members)
+                .map(|(network, members)| NetworkBackup { network, members })
+                .collect(),
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let backup: Self =
+            serde_json::from_str(text).map_err(|e| format!("Invalid backup archive: {}", e))?;
+        if backup.format_version > FORMAT_VERSION {
+            return Err(format!(
+                "Backup format version {} is newer than the supported {}, update the tool",
+                backup.format_version, FORMAT_VERSION
+            ));
+        }
+        Ok(backup)
+    }
+
+    pub fn member_count(&self) -> usize {
+        self.networks.iter().map(|x| x.members.len()).sum()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Create,
+    Overwrite,
+}
+
+pub enum Item {
+    Network(NetworkResponse),
+    Member(MemberResponse),
+}
+
+/// One network or member to write during a restore.
+pub struct Step {
+    pub change: Change,
+    pub item: Item,
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let change = match self.change {
+            Change::Create => "+",
+            Change::Overwrite => "~",
+        };
+        match &self.item {
+            Item::Network(network) => write!(f, "{} network {}", change, network),
+            Item::Member(member) => write!(f, "{}   member {}", change, member),
+        }
+    }
+}
+
+impl Step {
+    pub async fn apply(&self, client: &APIClient) -> Result<(), Error> {
+        match &self.item {
+            Item::Network(network) => {
+                client
+                    .edit_network(&network.id, EditNetwork::from(network))
+                    .await
+            }
+            Item::Member(member) => {
+                client
+                    .edit_member(&member.nwid, &member.id, EditMember::from(member))
+                    .await
+            }
+        }
+    }
+}
+
+/// Network ID moved to the controller `address`, the controller only serves its own networks.
+fn move_network_id(id: &str, address: &str) -> String {
+    match id.get(address.len()..) {
+        Some(suffix) => format!("{}{}", address, suffix),
+        None => id.to_string(),
+    }
+}
+
+/// Steps that recreate the backup on the controller with node ID `address`,
+/// networks come before their members.
+pub async fn plan(client: &APIClient, backup: &Backup, address: &str) -> Result<Vec<Step>, Error> {
+    let existing = client.networks().await?.into_iter().collect::<HashSet<_>>();
+
+    let mut steps = Vec::new();
+    for NetworkBackup { network, members } in &backup.networks {
+        let id = move_network_id(&network.id, address);
+        let (change, current) = if existing.contains(&id) {
+            let current = client.members(&id).await?;
+            (Change::Overwrite, current.into_keys().collect())
+        } else {
+            (Change::Create, HashSet::new())
+        };
+
+        steps.push(Step {
+            change,
+            item: Item::Network(NetworkResponse {
+                id: id.clone(),
+                ..network.clone()
+            }),
+        });
+        steps.extend(members.iter().map(|member| Step {
+            change: if current.contains(&member.id) {
+                Change::Overwrite
+            } else {
+                Change::Create
+            },
+            item: Item::Member(MemberResponse {
+                nwid: id.clone(),
+                ..member.clone()
+            }),
+        }));
+    }
+    Ok(steps)
+}
@@ -0,0 +1,269 @@
+use crate::dto::{MemberResponse, NetworkResponse};
+use crate::output::Tabular;
+use ipnet::IpNet;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    DuplicateIp,
+    PoolOutsideRoutes,
+    OverlappingPools,
+    OverlappingRoutes,
+    IpOutsidePools,
+}
+
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Self::DuplicateIp => "duplicate IP",
+            Self::PoolOutsideRoutes => "pool outside routes",
+            Self::OverlappingPools => "overlapping pools",
+            Self::OverlappingRoutes => "overlapping routes",
+            Self::IpOutsidePools => "IP outside pools",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Issue {
+    pub network: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.network, self.kind, self.message)
+    }
+}
+
+impl Tabular for Issue {
+    fn headers() -> Vec<&'static str> {
+        vec!["NETWORK", "KIND", "MESSAGE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.network.clone(),
+            self.kind.to_string(),
+            self.message.clone(),
+        ]
+    }
+}
+
+fn label(member: &MemberResponse) -> String {
+    match &member.name {
+        Some(name) if !name.is_empty() => format!("{} ({})", name, member.id),
+        _ => member.id.clone(),
+    }
+}
+
+/// Pairs of items at different positions, each pair once.
+fn pairs<T>(items: &[T]) -> impl Iterator<Item = (&T, &T)> {
+    items
+        .iter()
+        .enumerate()
+        .flat_map(move |(i, a)| items[i + 1..].iter().map(move |b| (a, b)))
+}
+
+/// Reports address conflicts and inconsistent pools and routes of a network.
+pub fn check(network: &NetworkResponse, members: &[MemberResponse]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut issue = |kind, message| {
+        issues.push(Issue {
+            network: network.id.clone(),
+            kind,
+            message,
+        })
+    };
+
+    let routes = network
+        .routes
+        .iter()
+        .filter_map(|x| x.target.parse::<IpNet>().ok().map(|target| (x, target)))
+        .collect::<Vec<_>>();
+    let pools = network
+        .ip_assignment_pools
+        .iter()
+        .filter_map(|x| x.range().ok().map(|range| (x, range)))
+        .collect::<Vec<_>>();
+
+    let mut holders: BTreeMap<IpAddr, Vec<String>> = BTreeMap::new();
+    for member in members {
+        for ip in member
+            .ip_assignments
+            .iter()
+            .filter_map(|x| x.parse::<IpAddr>().ok())
+        {
+            holders.entry(ip).or_default().push(label(member));
+            if !pools
+                .iter()
+                .any(|(_, (start, end))| (*start..=*end).contains(&ip))
+            {
+                issue(
+                    IssueKind::IpOutsidePools,
+                    format!("{} of {} is outside all pools", ip, label(member)),
+                );
+            }
+        }
+    }
+    for (ip, members) in holders.iter().filter(|x| x.1.len() > 1) {
+        issue(
+            IssueKind::DuplicateIp,
+            format!("{} is assigned to {}", ip, members.join(" and ")),
+        );
+    }
+
+    // Routes through a gateway do not make pool addresses reachable on the network
+    for (pool, (start, end)) in &pools {
+        if !routes.iter().any(|(route, target)| {
+            route.via.is_none() && target.contains(start) && target.contains(end)
+        }) {
+            issue(
+                IssueKind::PoolOutsideRoutes,
+                format!("pool {} is not covered by any managed route", pool),
+            );
+        }
+    }
+    for ((a, (a_start, a_end)), (b, (b_start, b_end))) in pairs(&pools) {
+        if a_start.is_ipv4() == b_start.is_ipv4() && a_start <= b_end && b_start <= a_end {
+            issue(
+                IssueKind::OverlappingPools,
+                format!("pools {} and {} overlap", a, b),
+            );
+        }
+    }
+
+    // A more specific route through a different gateway is a deliberate setup,
+    // only overlaps through the same gateway or the same target are reported
+    for ((a, a_target), (b, b_target)) in pairs(&routes) {
+        let overlap =
+            a_target.contains(&b_target.network()) || b_target.contains(&a_target.network());
+        if overlap && (a.via == b.via || a_target == b_target) {
+            issue(
+                IssueKind::OverlappingRoutes,
+                format!("routes {} and {} overlap", a, b),
+            );
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::tests::{empty_network, member};
+
+    fn network(pools: &[&str], routes: &[&str]) -> NetworkResponse {
+        NetworkResponse {
+            ip_assignment_pools: pools.iter().map(|x| x.parse().unwrap()).collect(),
+            routes: routes.iter().map(|x| x.parse().unwrap()).collect(),
+            ..empty_network()
+        }
+    }
+
+    fn messages(issues: &[Issue], kind: IssueKind) -> Vec<String> {
+        issues
+            .iter()
+            .filter(|x| x.kind == kind)
+            .map(|x| x.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn reports_nothing_for_a_consistent_network() {
+        let network = network(
+            &["10.0.0.1-10.0.0.254", "fd00::1-fd00::ffff"],
+            &["10.0.0.0/24", "fd00::/64", "192.168.0.0/16@10.0.0.1"],
+        );
+        let members = vec![
+            member("1111111111", &["10.0.0.1", "fd00::1"]),
+            member("2222222222", &["10.0.0.2"]),
+        ];
+        let issues = check(&network, &members);
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn reports_duplicate_ips_once_with_all_holders() {
+        let network = network(&["10.0.0.1-10.0.0.254"], &["10.0.0.0/24"]);
+        let members = vec![
+            member("1111111111", &["10.0.0.5"]),
+            member("2222222222", &["10.0.0.5"]),
+            member("3333333333", &["10.0.0.5", "10.0.0.6"]),
+        ];
+        assert_eq!(
+            messages(&check(&network, &members), IssueKind::DuplicateIp),
+            vec!["10.0.0.5 is assigned to 1111111111 and 2222222222 and 3333333333"]
+        );
+    }
+
+    #[test]
+    fn reports_ips_outside_pools() {
+        let network = network(&["10.0.0.1-10.0.0.100"], &["10.0.0.0/24"]);
+        let members = vec![member("1111111111", &["10.0.0.100", "10.0.0.101"])];
+        assert_eq!(
+            messages(&check(&network, &members), IssueKind::IpOutsidePools),
+            vec!["10.0.0.101 of 1111111111 is outside all pools"]
+        );
+    }
+
+    #[test]
+    fn does_not_count_routes_via_a_gateway_as_covering_pools() {
+        let network = network(
+            &["10.0.0.1-10.0.0.254", "10.0.1.1-10.0.2.254"],
+            &["10.0.0.0/24@10.0.9.1", "10.0.1.0/24"],
+        );
+        assert_eq!(
+            messages(&check(&network, &[]), IssueKind::PoolOutsideRoutes),
+            vec![
+                "pool 10.0.0.1 -> 10.0.0.254 is not covered by any managed route",
+                "pool 10.0.1.1 -> 10.0.2.254 is not covered by any managed route",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_overlapping_pools_of_one_family() {
+        let network = network(
+            &[
+                "10.0.0.1-10.0.0.100",
+                "10.0.0.100-10.0.0.200",
+                "fd00::1-fd00::ff",
+            ],
+            &["10.0.0.0/24", "fd00::/64"],
+        );
+        assert_eq!(
+            messages(&check(&network, &[]), IssueKind::OverlappingPools),
+            vec!["pools 10.0.0.1 -> 10.0.0.100 and 10.0.0.100 -> 10.0.0.200 overlap"]
+        );
+    }
+
+    #[test]
+    fn reports_overlapping_routes_through_the_same_gateway_or_to_the_same_target() {
+        let network = network(
+            &[],
+            &[
+                "10.0.0.0/16",
+                "10.0.1.0/24",
+                "10.0.2.0/24@10.0.0.1",
+                "192.168.0.0/24@10.0.0.1",
+                "192.168.0.0/24@10.0.0.2",
+                "fd00::/64",
+            ],
+        );
+        assert_eq!(
+            messages(&check(&network, &[]), IssueKind::OverlappingRoutes),
+            vec![
+                "routes 10.0.0.0/16 via (null) and 10.0.1.0/24 via (null) overlap",
+                "routes 192.168.0.0/24 via 10.0.0.1 and 192.168.0.0/24 via 10.0.0.2 overlap",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1011 @@
+use crate::allocator::next_free_for;
+use crate::api::{APIClient, EditMember, EditNetwork};
+use crate::backup::{self, Backup, Change};
+use crate::check;
+use crate::compiler::{self, Program, TagDefinition};
+use crate::desired::{self, ActionKind, DesiredState};
+use crate::drift;
+use crate::dto::{
+    MAX_MTU, MIN_MTU, MemberResponse, NetworkDNS, NetworkIPAssignmentPool, NetworkResponse,
+    NetworkRoute, NetworkV4AssignMode, format_timestamp, is_valid_address, is_valid_domain,
+};
+use crate::filter::MemberFilter;
+use crate::output::{OutputFormat, print_item, print_list, print_value};
+use crate::rules;
+use crate::watch::{EventKind, Watcher};
+use chrono::Local;
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use dialoguer::Confirm;
+use std::error::Error;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io};
+
+/// Manage your ZeroTier controller from the terminal.
+///
+/// Without a subcommand the interactive menus are started.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Output format for listings and details [default: plain]
+    #[arg(short, long, global = true, value_enum)]
+    pub output: Option<OutputFormat>,
+    /// Controller profile from the config file
+    #[arg(short, long, global = true)]
+    pub profile: Option<String>,
+    /// Path to the config file
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Read the API token from this file, e.g. the service authtoken.secret
+    #[arg(long, global = true)]
+    pub token_file: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show the controller node status
+    Status,
+    /// Manage networks
+    #[command(subcommand)]
+    Network(NetworkCommand),
+    /// Manage network members
+    #[command(subcommand)]
+    Member(MemberCommand),
+    /// Report IP conflicts and inconsistent pools and routes, exits with 1 on issues
+    Check {
+        /// Networks to check, all networks when none are given
+        networks: Vec<String>,
+    },
+    /// Save all networks and members to a JSON archive
+    Backup {
+        /// Archive file, `-` writes to stdout
+        file: PathBuf,
+    },
+    /// Show the changes needed to reach a desired-state file
+    Plan {
+        /// YAML file with the desired networks and members
+        file: PathBuf,
+    },
+    /// Make the controller match a desired-state file
+    Apply {
+        /// YAML file with the desired networks and members
+        file: PathBuf,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Compare the controller with a backup snapshot or desired-state file, exits with 1 on drift
+    Drift {
+        /// Archive written by `backup` or a desired-state YAML file
+        file: PathBuf,
+    },
+    /// Poll the members and log join requests, authorization, IP changes and deletions
+    Watch {
+        /// Networks to watch, all networks when none are given
+        networks: Vec<String>,
+        /// Seconds between polls
+        #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+    /// Recreate networks and members from a backup archive
+    Restore {
+        /// Archive file, `-` reads from stdin
+        file: PathBuf,
+        /// Only show what would be created or overwritten
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NetworkCommand {
+    /// List all networks of the controller
+    List,
+    /// Show network details
+    Show { network: Option<String> },
+    /// List managed routes of a network
+    Routes { network: Option<String> },
+    /// List IP assignment pools of a network
+    Pools { network: Option<String> },
+    /// Show the flow rules of a network
+    Rules { network: Option<String> },
+    /// Replace the flow rules of a network with a JSON array or rules source
+    SetRules {
+        network: Option<String>,
+        /// File with the rules, `-` reads from stdin
+        #[arg(long)]
+        file: PathBuf,
+        /// Compile the file as rules language source instead of reading JSON
+        #[arg(long)]
+        source: bool,
+    },
+    /// Create a new network
+    Create,
+    /// Delete a network
+    Delete {
+        network: Option<String>,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Change network settings
+    Set {
+        network: Option<String>,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        private: Option<bool>,
+        /// Enable or disable Ethernet broadcast (ff:ff:ff:ff:ff:ff)
+        #[arg(long)]
+        broadcast: Option<bool>,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(MIN_MTU as i64..=MAX_MTU as i64))]
+        mtu: Option<u32>,
+        /// Maximum recipients of a multicast packet, 0 disables multicast
+        #[arg(long)]
+        multicast_limit: Option<u32>,
+        /// Node that receives remote trace events of the network members
+        #[arg(long, value_parser = parse_address)]
+        trace_target: Option<String>,
+        #[arg(long)]
+        trace_level: Option<u32>,
+        /// Disable remote tracing
+        #[arg(long, conflicts_with_all = ["trace_target", "trace_level"])]
+        clear_trace: bool,
+        /// Enable or disable ZeroTier managed IPv4 assignment
+        #[arg(long)]
+        v4_zt: Option<bool>,
+        /// Enable or disable ZeroTier managed IPv6 assignment from the pools
+        #[arg(long)]
+        v6_zt: Option<bool>,
+        /// Enable or disable 6PLANE addressing
+        #[arg(long = "v6-6plane")]
+        v6_six_plane: Option<bool>,
+        /// Enable or disable RFC4193 addressing
+        #[arg(long)]
+        v6_rfc4193: Option<bool>,
+        /// IP assignment pool as START-END, replaces existing pools (repeatable)
+        #[arg(long = "pool", value_name = "START-END")]
+        pools: Vec<NetworkIPAssignmentPool>,
+        /// Remove all IP assignment pools
+        #[arg(long, conflicts_with = "pools")]
+        clear_pools: bool,
+        /// Managed route as TARGET[@VIA], replaces existing routes (repeatable)
+        #[arg(long = "route", value_name = "TARGET[@VIA]")]
+        routes: Vec<NetworkRoute>,
+        /// Remove all managed routes
+        #[arg(long, conflicts_with = "routes")]
+        clear_routes: bool,
+        /// DNS search domain pushed to members
+        #[arg(long, value_parser = parse_domain)]
+        dns_domain: Option<String>,
+        /// DNS server address, replaces existing servers (repeatable)
+        #[arg(long = "dns-server", value_name = "IP")]
+        dns_servers: Vec<IpAddr>,
+        /// Remove the DNS configuration
+        #[arg(long, conflicts_with_all = ["dns_domain", "dns_servers"])]
+        clear_dns: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MemberCommand {
+    /// List members of a network
+    List {
+        network: Option<String>,
+        #[command(flatten)]
+        filter: MemberFilter,
+    },
+    /// Show member details
+    Show { network: String, member: String },
+    /// Authorize a member
+    Authorize { network: String, member: String },
+    /// Deauthorize a member
+    Deauthorize { network: String, member: String },
+    /// Replace the IP assignments of a member
+    SetIp {
+        network: String,
+        member: String,
+        #[arg(required_unless_present = "auto")]
+        ips: Vec<IpAddr>,
+        /// Add the next free address of the IP assignment pools
+        #[arg(long)]
+        auto: bool,
+    },
+    /// Rename a member, an empty name removes it
+    Rename {
+        network: String,
+        member: String,
+        name: String,
+    },
+    /// Change member flags
+    Set {
+        network: String,
+        member: String,
+        /// Allow the member to bridge other Ethernet devices into the network
+        #[arg(long)]
+        active_bridge: Option<bool>,
+        /// Do not assign IPs from the pools to the member automatically
+        #[arg(long)]
+        no_auto_assign_ips: Option<bool>,
+        /// Exempt the member from SSO authentication
+        #[arg(long)]
+        sso_exempt: Option<bool>,
+    },
+    /// Set a tag of a member, the value is a number, an enum name or flag names
+    SetTag {
+        network: String,
+        member: String,
+        tag: String,
+        value: String,
+    },
+    /// Remove a tag from a member
+    RemoveTag {
+        network: String,
+        member: String,
+        tag: String,
+    },
+    /// Grant a capability to a member
+    Grant {
+        network: String,
+        member: String,
+        capability: String,
+    },
+    /// Revoke a capability from a member
+    Revoke {
+        network: String,
+        member: String,
+        capability: String,
+    },
+    /// Delete a member
+    Delete {
+        network: String,
+        member: String,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+pub struct Context {
+    pub client: APIClient,
+    pub format: OutputFormat,
+    pub default_network: Option<String>,
+}
+
+impl Context {
+    /// Falls back to the `default_network` of the profile.
+    fn network_id(&self, network: Option<String>) -> Result<String, Box<dyn Error>> {
+        network
+            .or_else(|| self.default_network.clone())
+            .ok_or_else(|| "No network given and the profile has no default_network".into())
+    }
+}
+
+/// Runs a subcommand and returns the exit status.
+pub async fn run(ctx: &Context, command: Command) -> Result<i32, Box<dyn Error>> {
+    match command {
+        Command::Status => print_item(ctx.format, &ctx.client.status().await?, |x| x.to_string()),
+        Command::Network(command) => network(ctx, command).await?,
+        Command::Member(command) => member(ctx, command).await?,
+        Command::Check { networks } => return check(ctx, networks).await,
+        Command::Backup { file } => {
+            let backup = Backup::fetch(&ctx.client).await?;
+            let text = serde_json::to_string_pretty(&backup)?;
+            if file == Path::new("-") {
+                println!("{}", text);
+            } else {
+                fs::write(&file, text)
+                    .map_err(|e| format!("Cannot write {}: {}", file.display(), e))?;
+                println!(
+                    "{}",
+                    format!(
+                        "✔ Saved {} networks and {} members to {}",
+                        backup.networks.len(),
+                        backup.member_count(),
+                        file.display()
+                    )
+                    .bright_green()
+                );
+            }
+        }
+        Command::Restore { file, dry_run, yes } => restore(ctx, &file, dry_run, yes).await?,
+        Command::Watch { networks, interval } => watch(ctx, networks, interval).await?,
+        Command::Drift { file } => return drift(ctx, &file).await,
+        Command::Plan { file } => apply(ctx, &file, true, false).await?,
+        Command::Apply { file, yes } => apply(ctx, &file, false, yes).await?,
+    }
+    Ok(0)
+}
+
+/// Logs member events until interrupted, one JSON object per line with `--output json`.
+async fn watch(ctx: &Context, networks: Vec<String>, interval: u64) -> Result<(), Box<dyn Error>> {
+    let json = ctx.format == OutputFormat::Json;
+    let mut watcher = Watcher::new(&ctx.client, networks).await?;
+    if !json {
+        let pending = watcher.members().filter(|x| !x.authorized).count();
+        println!(
+            "{}",
+            format!(
+                "👀 Watching {} networks with {} members ({} waiting for authorization), polling every {}s",
+                watcher.network_count(),
+                watcher.members().count(),
+                pending,
+                interval
+            )
+            .bright_blue()
+        );
+    }
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let events = match watcher.poll(&ctx.client).await {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("❌ Request failed: {}", e);
+                continue;
+            }
+        };
+
+        for event in events {
+            if json {
+                println!("{}", serde_json::to_string(&event)?);
+                continue;
+            }
+            let line = format!("[{}] {}", Local::now().format("%H:%M:%S"), event);
+            match event.event {
+                EventKind::JoinRequest => println!("{}", line.yellow()),
+                EventKind::Added | EventKind::Authorized => println!("{}", line.bright_green()),
+                EventKind::Deauthorized | EventKind::Deleted => println!("{}", line.bright_red()),
+                EventKind::IpsChanged => println!("{}", line.bright_blue()),
+            }
+        }
+    }
+}
+
+async fn drift(ctx: &Context, file: &Path) -> Result<i32, Box<dyn Error>> {
+    let text = read_input(file)?;
+    let live = Backup::fetch(&ctx.client).await?;
+
+    // Archives are JSON objects with a format version, anything else is a desired state
+    let is_backup = serde_json::from_str::<serde_json::Value>(&text)
+        .is_ok_and(|x| x.get("format_version").is_some());
+    let drift = if is_backup {
+        drift::from_snapshot(&Backup::parse(&text)?.networks, &live.networks)
+    } else {
+        let desired: DesiredState = serde_yaml::from_str(&text)
+            .map_err(|e| format!("Invalid desired state {}: {}", file.display(), e))?;
+        drift::from_desired(&desired, &live.networks)?
+    };
+
+    if drift.is_empty() && ctx.format == OutputFormat::Plain {
+        println!(
+            "{}",
+            format!("✔ No drift from {}", file.display()).bright_green()
+        );
+        return Ok(0);
+    }
+    print_list(ctx.format, &drift, |x| {
+        format!("⚠ {}", x).yellow().to_string()
+    });
+    Ok(if drift.is_empty() { 0 } else { 1 })
+}
+
+/// Prints the plan for a desired-state file and applies it unless `plan_only`.
+async fn apply(
+    ctx: &Context,
+    file: &Path,
+    plan_only: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let desired = DesiredState::load(file)?;
+    let live = Backup::fetch(&ctx.client).await?;
+    let actions = desired::plan(&desired, &live.networks)?;
+
+    if actions.is_empty() {
+        println!(
+            "{}",
+            format!("✔ No changes, the controller matches {}", file.display()).bright_green()
+        );
+        return Ok(());
+    }
+
+    for action in &actions {
+        match action.kind {
+            ActionKind::Create => println!("{}", action.to_string().bright_green()),
+            ActionKind::Update => println!("{}", action.to_string().yellow()),
+            ActionKind::Delete => println!("{}", action.to_string().bright_red()),
+        }
+    }
+    let count = |kind| actions.iter().filter(|x| x.kind == kind).count();
+    println!(
+        "{} to create, {} to update, {} to delete",
+        count(ActionKind::Create),
+        count(ActionKind::Update),
+        count(ActionKind::Delete)
+    );
+
+    if plan_only
+        || !yes
+            && !Confirm::new()
+                .with_prompt("Apply these changes?")
+                .interact()?
+    {
+        return Ok(());
+    }
+
+    for action in &actions {
+        action.apply(&ctx.client).await?;
+    }
+    println!(
+        "{}",
+        format!("✔ Applied {} changes", actions.len()).bright_green()
+    );
+    Ok(())
+}
+
+async fn restore(
+    ctx: &Context,
+    file: &Path,
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let backup = Backup::parse(&read_input(file)?)?;
+    let status = ctx.client.status().await?;
+
+    println!(
+        "Backup of {} from {}, made by version {}",
+        backup.controller,
+        format_timestamp(backup.created),
+        backup.tool_version
+    );
+    if backup.controller != status.address {
+        println!(
+            "{}",
+            format!(
+                "⚠ The backup was taken from another controller, network IDs are moved to {}",
+                status.address
+            )
+            .yellow()
+        );
+    }
+
+    let steps = backup::plan(&ctx.client, &backup, &status.address).await?;
+    for step in &steps {
+        match step.change {
+            Change::Create => println!("{}", step.to_string().bright_green()),
+            Change::Overwrite => println!("{}", step.to_string().yellow()),
+        }
+    }
+    let overwrites = steps
+        .iter()
+        .filter(|x| x.change == Change::Overwrite)
+        .count();
+    println!(
+        "{} to create, {} to overwrite",
+        steps.len() - overwrites,
+        overwrites
+    );
+
+    if dry_run
+        || steps.is_empty()
+        || !yes
+            && !Confirm::new()
+                .with_prompt("Restore the backup?")
+                .interact()?
+    {
+        return Ok(());
+    }
+
+    for step in &steps {
+        step.apply(&ctx.client).await?;
+    }
+    println!("{}", "✔ Backup restored".bright_green());
+    Ok(())
+}
+
+async fn check(ctx: &Context, networks: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let networks = if networks.is_empty() {
+        ctx.client.networks().await?
+    } else {
+        networks
+    };
+
+    let mut issues = Vec::new();
+    for id in &networks {
+        let network = ctx.client.network(id).await?;
+        let members = ctx.client.members_detailed(id).await?;
+        issues.extend(check::check(&network, &members));
+    }
+
+    if issues.is_empty() && ctx.format == OutputFormat::Plain {
+        println!("{}", "✔ No issues found".bright_green());
+        return Ok(0);
+    }
+    print_list(ctx.format, &issues, |x| {
+        format!("⚠ {}", x).yellow().to_string()
+    });
+    Ok(if issues.is_empty() { 0 } else { 1 })
+}
+
+async fn network(ctx: &Context, command: NetworkCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        NetworkCommand::List => {
+            let networks = ctx.client.networks_detailed().await?;
+            print_list(ctx.format, &networks, |x| x.to_string());
+        }
+        NetworkCommand::Show { network } => {
+            let network = ctx.network_id(network)?;
+            print_item(ctx.format, &ctx.client.network(&network).await?, |x| {
+                x.details()
+            });
+        }
+        NetworkCommand::Routes { network } => {
+            let network = ctx.network_id(network)?;
+            let network = ctx.client.network(&network).await?;
+            print_list(ctx.format, &network.routes, |x| x.to_string());
+        }
+        NetworkCommand::Pools { network } => {
+            let network = ctx.network_id(network)?;
+            let network = ctx.client.network(&network).await?;
+            print_list(ctx.format, &network.ip_assignment_pools, |x| x.to_string());
+        }
+        NetworkCommand::Rules { network } => {
+            let network = ctx.network_id(network)?;
+            let network = ctx.client.network(&network).await?;
+            print_value(ctx.format, &network.rules, compiler::source(&network));
+        }
+        NetworkCommand::SetRules {
+            network,
+            file,
+            source,
+        } => {
+            let network = ctx.network_id(network)?;
+            let text = read_input(&file)?;
+            let current = ctx.client.network(&network).await?;
+            let data = if source {
+                source_rules(&current, text)?
+            } else {
+                EditNetwork {
+                    rules: rules::parse(&text)?,
+                    rules_source: String::new(),
+                    ..EditNetwork::from(&current)
+                }
+            };
+            ctx.client.edit_network(&network, data).await?;
+            println!("{}", "✔ Network updated".bright_green());
+        }
+        NetworkCommand::Create => {
+            let status = ctx.client.status().await?;
+            let network = ctx.client.create_network(&status.address).await?;
+            print_item(ctx.format, &network, |x| {
+                format!("⚡ Network created: {}", x.id)
+            });
+        }
+        NetworkCommand::Delete { network, yes } => {
+            let network = ctx.network_id(network)?;
+            if !yes
+                && !Confirm::new()
+                    .with_prompt(format!("Are you sure want to delete network {}?", network))
+                    .interact()?
+            {
+                return Ok(());
+            }
+            ctx.client.delete_network(&network).await?;
+            println!("{}", "✔ Network deleted".bright_green());
+        }
+        NetworkCommand::Set {
+            network,
+            name,
+            private,
+            broadcast,
+            mtu,
+            multicast_limit,
+            trace_target,
+            trace_level,
+            clear_trace,
+            v4_zt,
+            v6_zt,
+            v6_six_plane,
+            v6_rfc4193,
+            pools,
+            clear_pools,
+            routes,
+            clear_routes,
+            dns_domain,
+            dns_servers,
+            clear_dns,
+        } => {
+            let network = ctx.network_id(network)?;
+            let current = ctx.client.network(&network).await?;
+            let mut data = EditNetwork::from(&current);
+            if let Some(name) = name {
+                data.name = Some(name);
+            }
+            if let Some(private) = private {
+                data.private = private;
+            }
+            if let Some(broadcast) = broadcast {
+                data.enable_broadcast = broadcast;
+            }
+            if let Some(mtu) = mtu {
+                data.mtu = mtu;
+            }
+            if let Some(limit) = multicast_limit {
+                data.multicast_limit = limit;
+            }
+            if let Some(target) = trace_target {
+                data.remote_trace_target = Some(target);
+            }
+            if let Some(level) = trace_level {
+                data.remote_trace_level = level;
+            }
+            if clear_trace {
+                data.remote_trace_target = None;
+                data.remote_trace_level = 0;
+            }
+            if let Some(zt) = v4_zt {
+                data.v4_assign_mode = NetworkV4AssignMode { zt };
+            }
+            if let Some(zt) = v6_zt {
+                data.v6_assign_mode.zt = zt;
+            }
+            if let Some(six_plane) = v6_six_plane {
+                data.v6_assign_mode.six_plane = six_plane;
+            }
+            if let Some(rfc4193) = v6_rfc4193 {
+                data.v6_assign_mode.rfc4193 = rfc4193;
+            }
+            if clear_pools || !pools.is_empty() {
+                data.ip_assignment_pools = pools;
+            }
+            if clear_routes || !routes.is_empty() {
+                data.routes = routes;
+            }
+            if let Some(domain) = dns_domain {
+                data.dns.domain = domain;
+            }
+            if !dns_servers.is_empty() {
+                data.dns.servers = dns_servers.iter().map(|x| x.to_string()).collect();
+            }
+            if clear_dns {
+                data.dns = NetworkDNS::default();
+            }
+            ctx.client.edit_network(&network, data).await?;
+            println!("{}", "✔ Network updated".bright_green());
+        }
+    }
+    Ok(())
+}
+
+async fn member(ctx: &Context, command: MemberCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        MemberCommand::List { network, filter } => {
+            let network = ctx.network_id(network)?;
+            let members = ctx.client.members_detailed(&network).await?;
+            let members = filter
+                .apply(&members)
+                .into_iter()
+                .map(|i| members[i].clone())
+                .collect::<Vec<MemberResponse>>();
+            print_list(ctx.format, &members, |x| {
+                format!("{} {}", if !x.authorized { "🔒" } else { "🔓" }, x)
+            });
+        }
+        MemberCommand::Show { network, member } => {
+            print_item(
+                ctx.format,
+                &ctx.client.member(&network, &member).await?,
+                |x| x.details(),
+            );
+        }
+        MemberCommand::Authorize { network, member } => {
+            set_authorized(ctx, &network, &member, true).await?
+        }
+        MemberCommand::Deauthorize { network, member } => {
+            set_authorized(ctx, &network, &member, false).await?
+        }
+        MemberCommand::SetIp {
+            network,
+            member,
+            mut ips,
+            auto,
+        } => {
+            let settings = ctx.client.network(&network).await?;
+            if auto {
+                let members = ctx.client.members_detailed(&network).await?;
+                let ip = next_free_for(&settings.ip_assignment_pools, &members, &member, &ips)
+                    .ok_or("No free address left in the IP assignment pools")?;
+                println!("{}", format!("⚡ Assigning {}", ip).bright_blue());
+                ips.push(ip);
+            }
+
+            for ip in &ips {
+                if !settings.routes.iter().any(|x| x.contains(ip)) {
+                    println!(
+                        "{}",
+                        format!("⚠ {} is outside every managed route of the network", ip).yellow()
+                    );
+                }
+            }
+
+            let current = ctx.client.member(&network, &member).await?;
+            ctx.client
+                .edit_member(
+                    &network,
+                    &member,
+                    EditMember {
+                        ip_assignments: ips.iter().map(|x| x.to_string()).collect(),
+                        ..EditMember::from(&current)
+                    },
+                )
+                .await?;
+            println!("{}", "✔ Member updated".bright_green());
+        }
+        MemberCommand::Rename {
+            network,
+            member,
+            name,
+        } => {
+            let current = ctx.client.member(&network, &member).await?;
+            ctx.client
+                .edit_member(
+                    &network,
+                    &member,
+                    EditMember {
+                        name: if name.is_empty() { None } else { Some(name) },
+                        ..EditMember::from(&current)
+                    },
+                )
+                .await?;
+            println!("{}", "✔ Member updated".bright_green());
+        }
+        MemberCommand::Set {
+            network,
+            member,
+            active_bridge,
+            no_auto_assign_ips,
+            sso_exempt,
+        } => {
+            let current = ctx.client.member(&network, &member).await?;
+            let mut data = EditMember::from(&current);
+            if let Some(active_bridge) = active_bridge {
+                data.active_bridge = active_bridge;
+            }
+            if let Some(no_auto_assign_ips) = no_auto_assign_ips {
+                data.no_auto_assign_ips = no_auto_assign_ips;
+            }
+            if let Some(sso_exempt) = sso_exempt {
+                data.sso_exempt = sso_exempt;
+            }
+            ctx.client.edit_member(&network, &member, data).await?;
+            println!("{}", "✔ Member updated".bright_green());
+        }
+        MemberCommand::SetTag {
+            network,
+            member,
+            tag,
+            value,
+        } => set_tag(ctx, &network, &member, &tag, Some(&value)).await?,
+        MemberCommand::RemoveTag {
+            network,
+            member,
+            tag,
+        } => set_tag(ctx, &network, &member, &tag, None).await?,
+        MemberCommand::Grant {
+            network,
+            member,
+            capability,
+        } => set_capability(ctx, &network, &member, &capability, true).await?,
+        MemberCommand::Revoke {
+            network,
+            member,
+            capability,
+        } => set_capability(ctx, &network, &member, &capability, false).await?,
+        MemberCommand::Delete {
+            network,
+            member,
+            yes,
+        } => {
+            if !yes
+                && !Confirm::new()
+                    .with_prompt(format!("Are you sure want to delete member {}?", member))
+                    .interact()?
+            {
+                return Ok(());
+            }
+            ctx.client.delete_member(&network, &member).await?;
+            println!("{}", "✔ Member deleted".bright_green());
+        }
+    }
+    Ok(())
+}
+
+async fn set_authorized(
+    ctx: &Context,
+    network: &str,
+    member: &str,
+    authorized: bool,
+) -> Result<(), Box<dyn Error>> {
+    let current = ctx.client.member(network, member).await?;
+    ctx.client
+        .edit_member(
+            network,
+            member,
+            EditMember {
+                authorized,
+                ..EditMember::from(&current)
+            },
+        )
+        .await?;
+    println!("{}", "✔ Member updated".bright_green());
+    Ok(())
+}
+
+/// Network edit that replaces the rules, tags and capabilities with compiled `source`.
+fn source_rules(
+    current: &NetworkResponse,
+    source: String,
+) -> Result<EditNetwork, compiler::CompileError> {
+    let program = compiler::compile(&source)?;
+    Ok(EditNetwork {
+        capabilities: program.network_capabilities(),
+        tags: program.network_tags(),
+        rules: program.rules,
+        rules_source: source,
+        ..EditNetwork::from(current)
+    })
+}
+
+/// Tag definition and value for a tag and value given by name.
+fn resolve_tag<'a>(
+    program: &'a Program,
+    tag: &str,
+    value: Option<&str>,
+) -> Result<(&'a TagDefinition, Option<u32>), String> {
+    let tag = program
+        .tag(tag)
+        .ok_or_else(|| format!("tag `{}` is not defined in the network rules", tag))?;
+    let value = value
+        .map(|x| {
+            tag.value(x)
+                .ok_or_else(|| format!("invalid value `{}` for tag `{}`", x, tag.name))
+        })
+        .transpose()?;
+    Ok((tag, value))
+}
+
+/// Sets or with `value` of `None` removes a tag given by name.
+async fn set_tag(
+    ctx: &Context,
+    network: &str,
+    member: &str,
+    tag: &str,
+    value: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let program = Program::from_network(&ctx.client.network(network).await?);
+    let (tag, value) = resolve_tag(&program, tag, value)?;
+
+    let current = ctx.client.member(network, member).await?;
+    let tags = current.tags_with(tag.id, value);
+
+    ctx.client
+        .edit_member(
+            network,
+            member,
+            EditMember {
+                tags,
+                ..EditMember::from(&current)
+            },
+        )
+        .await?;
+    println!("{}", "✔ Member updated".bright_green());
+    Ok(())
+}
+
+async fn set_capability(
+    ctx: &Context,
+    network: &str,
+    member: &str,
+    capability: &str,
+    granted: bool,
+) -> Result<(), Box<dyn Error>> {
+    let program = Program::from_network(&ctx.client.network(network).await?);
+    let capability = program.capability(capability).ok_or_else(|| {
+        format!(
+            "capability `{}` is not defined in the network rules",
+            capability
+        )
+    })?;
+
+    let current = ctx.client.member(network, member).await?;
+    let mut capabilities = current.capabilities.clone();
+    capabilities.retain(|x| *x != capability.id);
+    if granted {
+        capabilities.push(capability.id);
+    }
+
+    ctx.client
+        .edit_member(
+            network,
+            member,
+            EditMember {
+                capabilities,
+                ..EditMember::from(&current)
+            },
+        )
+        .await?;
+    println!("{}", "✔ Member updated".bright_green());
+    Ok(())
+}
+
+fn parse_address(s: &str) -> Result<String, String> {
+    if is_valid_address(s) {
+        Ok(s.to_lowercase())
+    } else {
+        Err(format!("invalid node address `{}`", s))
+    }
+}
+
+fn parse_domain(s: &str) -> Result<String, String> {
+    if is_valid_domain(s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!("invalid domain `{}`", s))
+    }
+}
+
+/// Reads a file, `-` reads stdin.
+fn read_input(path: &Path) -> Result<String, Box<dyn Error>> {
+    if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        return Ok(text);
+    }
+    fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::tests::{empty_network, stored};
+
+    #[test]
+    fn sets_tags_by_name_after_set_rules_source() {
+        let current = empty_network();
+        let source = "\
+tag department
+  id 1000
+  enum 100 sales
+  enum 200 engineering
+;
+cap superuser
+  id 2000
+  accept;
+;
+accept;
+";
+        let data = source_rules(&current, source.to_string()).unwrap();
+        let network = stored(NetworkResponse {
+            rules: data.rules,
+            tags: data.tags,
+            capabilities: data.capabilities,
+            rules_source: data.rules_source,
+            ..current
+        });
+        let program = Program::from_network(&network);
+
+        let (tag, value) = resolve_tag(&program, "department", Some("engineering")).unwrap();
+        assert_eq!((tag.id, value), (1000, Some(200)));
+        assert_eq!(program.capability("superuser").unwrap().id, 2000);
+        assert!(resolve_tag(&program, "department", Some("marketing")).is_err());
+        assert!(resolve_tag(&program, "location", None).is_err());
+    }
+}
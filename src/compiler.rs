@@ -0,0 +1,1362 @@
+//! Compiler and decompiler for the ZeroTier rules language.
+//!
+//! ```text
+//! # Allow only IPv4, IPv4 ARP and IPv6 Ethernet frames
+//! drop
+//!   not ethertype ipv4
+//!   and not ethertype arp
+//!   and not ethertype ipv6
+//! ;
+//!
+//! tag department
+//!   id 1000
+//!   enum 100 sales
+//!   enum 200 engineering
+//!   default 100
+//! ;
+//!
+//! cap superuser
+//!   id 2000
+//!   accept;
+//! ;
+//!
+//! accept;
+//! ```
+//!
+//! Macros and includes are not supported.
+
+use crate::dto::{MemberTag, NetworkCapability, NetworkResponse, NetworkTag, is_valid_address};
+use ipnet::IpNet;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+const ETHERTYPES: &[(&str, u64)] = &[
+    ("ipv4", 0x0800),
+    ("arp", 0x0806),
+    ("wol", 0x0842),
+    ("rarp", 0x8035),
+    ("appletalk", 0x809b),
+    ("aarp", 0x80f3),
+    ("ipx", 0x8137),
+    ("ipv6", 0x86dd),
+    ("mpls", 0x8847),
+    ("pppoe_disc", 0x8863),
+    ("pppoe", 0x8864),
+    ("lldp", 0x88cc),
+];
+
+const PROTOCOLS: &[(&str, u64)] = &[
+    ("icmp", 1),
+    ("igmp", 2),
+    ("tcp", 6),
+    ("udp", 17),
+    ("gre", 47),
+    ("esp", 50),
+    ("ah", 51),
+    ("icmp6", 58),
+    ("ospf", 89),
+    ("pim", 103),
+    ("vrrp", 112),
+    ("l2tp", 115),
+    ("sctp", 132),
+    ("udplite", 136),
+];
+
+const CHARACTERISTICS: &[(&str, u64)] = &[
+    ("inbound", 0x8000000000000000),
+    ("multicast", 0x4000000000000000),
+    ("broadcast", 0x2000000000000000),
+    ("ipauth", 0x1000000000000000),
+    ("macauth", 0x0800000000000000),
+    ("tcp_rs0", 0x0800),
+    ("tcp_rs1", 0x0400),
+    ("tcp_rs2", 0x0200),
+    ("tcp_ns", 0x0100),
+    ("tcp_cwr", 0x0080),
+    ("tcp_ece", 0x0040),
+    ("tcp_urg", 0x0020),
+    ("tcp_ack", 0x0010),
+    ("tcp_psh", 0x0008),
+    ("tcp_rst", 0x0004),
+    ("tcp_syn", 0x0002),
+    ("tcp_fin", 0x0001),
+];
+
+/// Match keywords that compare tags, with the rule type they compile to.
+const TAG_MATCHES: &[(&str, &str)] = &[
+    ("tdiff", "MATCH_TAGS_DIFFERENCE"),
+    ("tand", "MATCH_TAGS_BITWISE_AND"),
+    ("tor", "MATCH_TAGS_BITWISE_OR"),
+    ("txor", "MATCH_TAGS_BITWISE_XOR"),
+    ("teq", "MATCH_TAGS_EQUAL"),
+    ("tseq", "MATCH_TAG_SENDER"),
+    ("treq", "MATCH_TAG_RECEIVER"),
+];
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[derive(Debug, Clone)]
+pub struct TagDefinition {
+    pub name: String,
+    pub id: u32,
+    pub default: Option<u32>,
+    pub enums: BTreeMap<String, u32>,
+    /// Flag names with their bit index
+    pub flags: BTreeMap<String, u32>,
+}
+
+impl TagDefinition {
+    /// Parses a number, an enum name or comma separated flag names.
+    pub fn value(&self, text: &str) -> Option<u32> {
+        if let Some(value) = self.enums.get(text) {
+            return Some(*value);
+        }
+        if let Some(value) = parse_number(text) {
+            return u32::try_from(value).ok();
+        }
+        text.split(',')
+            .map(|x| self.flags.get(x).map(|bit| 1 << bit))
+            .try_fold(0, |acc, bit| Some(acc | bit?))
+    }
+
+    /// Enum name of the value if there is one, otherwise the number.
+    pub fn describe_value(&self, value: u32) -> String {
+        self.enums
+            .iter()
+            .find(|x| *x.1 == value)
+            .map(|x| x.0.clone())
+            .unwrap_or(value.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CapabilityDefinition {
+    pub name: String,
+    pub id: u32,
+    pub default: bool,
+    pub rules: Vec<Value>,
+}
+
+/// Result of compiling rules source.
+#[derive(Debug, Default)]
+pub struct Program {
+    pub rules: Vec<Value>,
+    pub tags: Vec<TagDefinition>,
+    pub capabilities: Vec<CapabilityDefinition>,
+}
+
+impl Program {
+    pub fn tag(&self, name: &str) -> Option<&TagDefinition> {
+        self.tags.iter().find(|x| x.name == name)
+    }
+
+    pub fn capability(&self, name: &str) -> Option<&CapabilityDefinition> {
+        self.capabilities.iter().find(|x| x.name == name)
+    }
+
+    /// Member tag as `name=value`, falling back to numbers for unknown tags.
+    pub fn describe_tag(&self, tag: &MemberTag) -> String {
+        match self.tags.iter().find(|x| x.id == tag.0) {
+            Some(definition) => format!("{}={}", definition.name, definition.describe_value(tag.1)),
+            None => tag.to_string(),
+        }
+    }
+
+    /// Tag definitions as posted to the controller.
+    pub fn network_tags(&self) -> Vec<NetworkTag> {
+        self.tags
+            .iter()
+            .map(|x| NetworkTag {
+                id: x.id,
+                default: x.default,
+            })
+            .collect()
+    }
+
+    /// Capability definitions as posted to the controller.
+    pub fn network_capabilities(&self) -> Vec<NetworkCapability> {
+        self.capabilities
+            .iter()
+            .map(|x| NetworkCapability {
+                id: x.id,
+                default: x.default,
+                rules: x.rules.clone(),
+            })
+            .collect()
+    }
+
+    /// Compiled `rulesSource` of a network, if it still produces the live rules.
+    fn from_source(network: &NetworkResponse) -> Option<Self> {
+        if network.rules_source.trim().is_empty() {
+            return None;
+        }
+        compile(&network.rules_source).ok().filter(|x| {
+            normalized(&x.rules, &x.network_tags(), &x.network_capabilities())
+                == normalized(&network.rules, &network.tags, &network.capabilities)
+        })
+    }
+
+    /// Rules, tags and capabilities of a network.
+    ///
+    /// Names are taken from `rulesSource` when it still compiles to the live rules,
+    /// otherwise they are derived from the ids, e.g. `tag_1000`.
+    pub fn from_network(network: &NetworkResponse) -> Self {
+        Self::from_source(network).unwrap_or_else(|| Self::named_after(network, &Self::default()))
+    }
+
+    /// Rules, tags and capabilities of a network, named after the definitions in `names`
+    /// with the same id.
+    pub fn named_after(network: &NetworkResponse, names: &Program) -> Self {
+        Self {
+            rules: network.rules.clone(),
+            tags: network
+                .tags
+                .iter()
+                .map(|x| match names.tags.iter().find(|y| y.id == x.id) {
+                    Some(definition) => TagDefinition {
+                        default: x.default,
+                        ..definition.clone()
+                    },
+                    None => TagDefinition {
+                        name: format!("tag_{}", x.id),
+                        id: x.id,
+                        default: x.default,
+                        enums: BTreeMap::new(),
+                        flags: BTreeMap::new(),
+                    },
+                })
+                .collect(),
+            capabilities: network
+                .capabilities
+                .iter()
+                .map(|x| CapabilityDefinition {
+                    name: match names.capabilities.iter().find(|y| y.id == x.id) {
+                        Some(definition) => definition.name.clone(),
+                        None => format!("cap_{}", x.id),
+                    },
+                    id: x.id,
+                    default: x.default,
+                    rules: x.rules.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Rule in one form for comparisons, the way the controller renders it.
+///
+/// The controller adds `not` and `or` to every rule, writes MAC addresses with colons
+/// and some masks and ranges as hex strings.
+fn normalize_rule(rule: &Value) -> Value {
+    let mut rule = rule.clone();
+    if let Some(object) = rule.as_object_mut() {
+        for name in ["not", "or"] {
+            if !object.get(name).is_some_and(|x| x.is_boolean()) {
+                object.insert(name.to_string(), json!(false));
+            }
+        }
+        if let Some(mac) = object.get("mac").and_then(|x| x.as_str()) {
+            let mac = format_mac(mac);
+            object.insert("mac".to_string(), json!(mac));
+        }
+        for name in ["mask", "start", "end"] {
+            if let Some(text) = object.get(name).and_then(|x| x.as_str()) {
+                let value = u64::from_str_radix(text.trim_start_matches("0x"), 16).ok();
+                if let Some(value) = value {
+                    object.insert(name.to_string(), json!(value));
+                }
+            }
+        }
+    }
+    rule
+}
+
+/// Rules, tags and capabilities in one form for comparisons, tags and capabilities by id.
+fn normalized(rules: &[Value], tags: &[NetworkTag], capabilities: &[NetworkCapability]) -> Value {
+    let normalize = |rules: &[Value]| rules.iter().map(normalize_rule).collect::<Vec<Value>>();
+    let mut tags = tags.iter().map(|x| (x.id, x.default)).collect::<Vec<_>>();
+    tags.sort();
+    let mut capabilities = capabilities
+        .iter()
+        .map(|x| (x.id, x.default, normalize(&x.rules)))
+        .collect::<Vec<_>>();
+    capabilities.sort_by_key(|x| x.0);
+    json!([normalize(rules), tags, capabilities])
+}
+
+/// Whether two networks have the same rules, tags and capabilities,
+/// however the controller rendered them.
+pub fn same_rules(a: &NetworkResponse, b: &NetworkResponse) -> bool {
+    normalized(&a.rules, &a.tags, &a.capabilities) == normalized(&b.rules, &b.tags, &b.capabilities)
+}
+
+/// MAC address in the controller's form, lowercase with colons, e.g. `01:23:45:67:89:ab`.
+fn format_mac(mac: &str) -> String {
+    let digits = mac.replace(':', "").to_lowercase();
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|x| String::from_utf8_lossy(x).into_owned())
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for (line, text) in source.lines().enumerate() {
+        let text = text.split('#').next().unwrap_or_default();
+        let mut current: Option<Token> = None;
+
+        for (column, c) in text.chars().enumerate() {
+            if c.is_whitespace() || c == ';' {
+                tokens.extend(current.take());
+                if c == ';' {
+                    tokens.push(Token {
+                        text: ";".to_string(),
+                        line: line + 1,
+                        column: column + 1,
+                    });
+                }
+                continue;
+            }
+            current
+                .get_or_insert_with(|| Token {
+                    text: String::new(),
+                    line: line + 1,
+                    column: column + 1,
+                })
+                .text
+                .push(c);
+        }
+        tokens.extend(current);
+    }
+
+    tokens
+}
+
+fn lookup(table: &[(&str, u64)], name: &str) -> Option<u64> {
+    table.iter().find(|x| x.0 == name).map(|x| x.1)
+}
+
+fn reverse_lookup(table: &[(&'static str, u64)], value: u64) -> Option<&'static str> {
+    table.iter().find(|x| x.1 == value).map(|x| x.0)
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    tags: Vec<TagDefinition>,
+    capabilities: Vec<CapabilityDefinition>,
+}
+
+impl Parser {
+    fn error(token: &Token, message: String) -> CompileError {
+        CompileError {
+            line: token.line,
+            column: token.column,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Next token, failing with `what` was expected at the end of input.
+    fn next(&mut self, what: &str) -> Result<Token, CompileError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => {
+                let (line, column) = self
+                    .tokens
+                    .last()
+                    .map(|x| (x.line, x.column + x.text.chars().count()))
+                    .unwrap_or((1, 1));
+                Err(CompileError {
+                    line,
+                    column,
+                    message: format!("expected {}, found end of input", what),
+                })
+            }
+        }
+    }
+
+    fn number(&mut self, what: &str) -> Result<u64, CompileError> {
+        let token = self.next(what)?;
+        parse_number(&token.text).ok_or_else(|| {
+            Self::error(&token, format!("expected {}, found `{}`", what, token.text))
+        })
+    }
+
+    fn small_number(&mut self, what: &str, max: u64) -> Result<u64, CompileError> {
+        let index = self.position;
+        let number = self.number(what)?;
+        if number > max {
+            return Err(Self::error(
+                &self.tokens[index],
+                format!("{} must be at most {}", what, max),
+            ));
+        }
+        Ok(number)
+    }
+
+    fn range(&mut self, what: &str, max: u64) -> Result<(u64, u64), CompileError> {
+        let token = self.next(what)?;
+        let (start, end) = match token.text.split_once('-') {
+            Some((start, end)) => (parse_number(start), parse_number(end)),
+            None => (parse_number(&token.text), parse_number(&token.text)),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end && end <= max => Ok((start, end)),
+            _ => Err(Self::error(
+                &token,
+                format!(
+                    "expected {} as N or N-M up to {}, found `{}`",
+                    what, max, token.text
+                ),
+            )),
+        }
+    }
+
+    fn address(&mut self) -> Result<String, CompileError> {
+        let token = self.next("a ZeroTier address")?;
+        let address = token.text.to_lowercase();
+        if is_valid_address(&address) {
+            Ok(address)
+        } else {
+            Err(Self::error(
+                &token,
+                format!(
+                    "expected a 10 digit ZeroTier address, found `{}`",
+                    token.text
+                ),
+            ))
+        }
+    }
+
+    fn named(&mut self, what: &str, table: &[(&str, u64)]) -> Result<u64, CompileError> {
+        let token = self.next(what)?;
+        lookup(table, &token.text.to_lowercase())
+            .or_else(|| parse_number(&token.text))
+            .ok_or_else(|| Self::error(&token, format!("unknown {} `{}`", what, token.text)))
+    }
+
+    fn program(&mut self) -> Result<Vec<Value>, CompileError> {
+        let mut rules = Vec::new();
+
+        while let Some(token) = self.peek() {
+            match token.text.as_str() {
+                ";" => self.position += 1,
+                "tag" => self.tag()?,
+                "cap" => self.capability()?,
+                "macro" | "include" => {
+                    return Err(Self::error(
+                        token,
+                        format!("`{}` is not supported", token.text),
+                    ));
+                }
+                _ => rules.extend(self.statement()?),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    fn name(&mut self, what: &str) -> Result<String, CompileError> {
+        let token = self.next(what)?;
+        let valid = token
+            .text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            && token
+                .text
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic());
+        if !valid {
+            return Err(Self::error(
+                &token,
+                format!("invalid {} `{}`", what, token.text),
+            ));
+        }
+        Ok(token.text.clone())
+    }
+
+    fn tag(&mut self) -> Result<(), CompileError> {
+        let start = self.position;
+        self.position += 1;
+        let name = self.name("tag name")?;
+        let mut tag = TagDefinition {
+            name,
+            id: 0,
+            default: None,
+            enums: BTreeMap::new(),
+            flags: BTreeMap::new(),
+        };
+        let mut id = None;
+
+        loop {
+            let token = self.next("`;` to end the tag")?;
+            match token.text.as_str() {
+                ";" => break,
+                "id" => id = Some(self.small_number("tag id", u32::MAX as u64)? as u32),
+                "enum" => {
+                    let value = self.small_number("enum value", u32::MAX as u64)? as u32;
+                    let name = self.name("enum name")?;
+                    tag.enums.insert(name, value);
+                }
+                "flag" => {
+                    let bit = self.small_number("flag bit", 31)? as u32;
+                    let name = self.name("flag name")?;
+                    tag.flags.insert(name, bit);
+                }
+                "default" => {
+                    let token = self.next("default value")?;
+                    let value = tag
+                        .enums
+                        .get(&token.text)
+                        .copied()
+                        .or_else(|| parse_number(&token.text).and_then(|x| u32::try_from(x).ok()))
+                        .ok_or_else(|| {
+                            Self::error(
+                                &token,
+                                format!(
+                                    "expected a number up to {} or enum name, found `{}`",
+                                    u32::MAX,
+                                    token.text
+                                ),
+                            )
+                        })?;
+                    tag.default = Some(value);
+                }
+                _ => {
+                    return Err(Self::error(
+                        &token,
+                        format!(
+                            "expected `id`, `enum`, `flag`, `default` or `;`, found `{}`",
+                            token.text
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let token = &self.tokens[start];
+        tag.id = id.ok_or_else(|| Self::error(token, format!("tag `{}` has no id", tag.name)))?;
+        if self
+            .tags
+            .iter()
+            .any(|x| x.name == tag.name || x.id == tag.id)
+        {
+            return Err(Self::error(
+                token,
+                format!("tag `{}` or id {} is already defined", tag.name, tag.id),
+            ));
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    fn capability(&mut self) -> Result<(), CompileError> {
+        let start = self.position;
+        self.position += 1;
+        let name = self.name("capability name")?;
+        let mut id = None;
+        let mut default = false;
+        let mut rules = Vec::new();
+
+        loop {
+            let token = self.next("`;` to end the capability")?;
+            match token.text.as_str() {
+                ";" => break,
+                "id" => id = Some(self.small_number("capability id", u32::MAX as u64)? as u32),
+                "default" => default = true,
+                _ => {
+                    self.position -= 1;
+                    rules.extend(self.statement()?);
+                }
+            }
+        }
+
+        let token = &self.tokens[start];
+        let id =
+            id.ok_or_else(|| Self::error(token, format!("capability `{}` has no id", name)))?;
+        if self
+            .capabilities
+            .iter()
+            .any(|x| x.name == name || x.id == id)
+        {
+            return Err(Self::error(
+                token,
+                format!("capability `{}` or id {} is already defined", name, id),
+            ));
+        }
+        self.capabilities.push(CapabilityDefinition {
+            name,
+            id,
+            default,
+            rules,
+        });
+        Ok(())
+    }
+
+    /// An action followed by its matches, e.g. `drop not ethertype ipv4;`.
+    fn statement(&mut self) -> Result<Vec<Value>, CompileError> {
+        let token = self.next("an action")?;
+        let action = match token.text.as_str() {
+            "drop" => json!({ "type": "ACTION_DROP", "not": false, "or": false }),
+            "accept" => json!({ "type": "ACTION_ACCEPT", "not": false, "or": false }),
+            "break" => json!({ "type": "ACTION_BREAK", "not": false, "or": false }),
+            "debug" => json!({ "type": "ACTION_DEBUG_LOG", "not": false, "or": false }),
+            kind @ ("tee" | "watch") => {
+                let kind = if kind == "tee" {
+                    "ACTION_TEE"
+                } else {
+                    "ACTION_WATCH"
+                };
+                let length = self.small_number("length", u16::MAX as u64)?;
+                let address = self.address()?;
+                json!({
+                    "type": kind,
+                    "address": address,
+                    "flags": 0,
+                    "length": length,
+                    "not": false,
+                    "or": false,
+                })
+            }
+            "redirect" => {
+                let address = self.address()?;
+                json!({
+                    "type": "ACTION_REDIRECT",
+                    "address": address,
+                    "flags": 0,
+                    "not": false,
+                    "or": false,
+                })
+            }
+            _ => {
+                return Err(Self::error(
+                    &token,
+                    format!("expected an action, found `{}`", token.text),
+                ));
+            }
+        };
+
+        let mut rules = Vec::new();
+        let mut or = false;
+        let mut not = false;
+        loop {
+            let token = self.next("`;` to end the rule")?;
+            match token.text.as_str() {
+                ";" if or || not => {
+                    return Err(Self::error(
+                        &token,
+                        "expected a match, found `;`".to_string(),
+                    ));
+                }
+                ";" => break,
+                "and" => {}
+                "or" => or = true,
+                "not" => not = true,
+                _ => {
+                    let index = self.position - 1;
+                    let mut rule = self.matcher(index)?;
+                    rule["not"] = json!(not);
+                    rule["or"] = json!(or);
+                    rules.push(rule);
+                    or = false;
+                    not = false;
+                }
+            }
+        }
+
+        rules.push(action);
+        Ok(rules)
+    }
+
+    fn tag_reference(&mut self) -> Result<Option<usize>, CompileError> {
+        let token = self.next("a tag")?;
+        if let Some(index) = self.tags.iter().position(|x| x.name == token.text) {
+            return Ok(Some(index));
+        }
+        match parse_number(&token.text) {
+            Some(_) => {
+                self.position -= 1;
+                Ok(None)
+            }
+            None => Err(Self::error(
+                &token,
+                format!("tag `{}` is not defined", token.text),
+            )),
+        }
+    }
+
+    /// Value of a tag match, a number or an enum or flag name of the tag.
+    fn tag_value(&mut self, tag: Option<usize>) -> Result<u64, CompileError> {
+        let token = self.next("a tag value")?;
+        if let Some(tag) = tag.map(|x| &self.tags[x]) {
+            if let Some(value) = tag.enums.get(&token.text) {
+                return Ok(*value as u64);
+            }
+            if let Some(bit) = tag.flags.get(&token.text) {
+                return Ok(1 << bit);
+            }
+        }
+        parse_number(&token.text)
+            .filter(|x| *x <= u32::MAX as u64)
+            .ok_or_else(|| {
+                Self::error(
+                    &token,
+                    format!("expected a tag value, found `{}`", token.text),
+                )
+            })
+    }
+
+    fn matcher(&mut self, index: usize) -> Result<Value, CompileError> {
+        let keyword = self.tokens[index].text.clone();
+        let rule = match keyword.as_str() {
+            "ztsrc" => json!({ "type": "MATCH_SOURCE_ZEROTIER_ADDRESS", "zt": self.address()? }),
+            "ztdest" => json!({ "type": "MATCH_DEST_ZEROTIER_ADDRESS", "zt": self.address()? }),
+            "vlan" => {
+                json!({ "type": "MATCH_VLAN_ID", "vlanId": self.small_number("VLAN id", 4095)? })
+            }
+            "vlanpcp" => {
+                json!({ "type": "MATCH_VLAN_PCP", "vlanPcp": self.small_number("VLAN PCP", 7)? })
+            }
+            "vlandei" => {
+                json!({ "type": "MATCH_VLAN_DEI", "vlanDei": self.small_number("VLAN DEI", 1)? })
+            }
+            "macsrc" | "macdest" => {
+                let token = self.next("a MAC address")?;
+                let mac = token.text.to_lowercase();
+                let digits = mac.replace(':', "");
+                if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(Self::error(
+                        &token,
+                        format!("expected a MAC address, found `{}`", token.text),
+                    ));
+                }
+                let kind = if keyword == "macsrc" {
+                    "MATCH_MAC_SOURCE"
+                } else {
+                    "MATCH_MAC_DEST"
+                };
+                json!({ "type": kind, "mac": format_mac(&mac) })
+            }
+            "ipsrc" | "ipdest" => {
+                let token = self.next("an IP network")?;
+                let net = token
+                    .text
+                    .parse::<IpNet>()
+                    .or_else(|_| token.text.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        Self::error(
+                            &token,
+                            format!("expected an IP network, found `{}`", token.text),
+                        )
+                    })?;
+                let kind = match (keyword == "ipsrc", net) {
+                    (true, IpNet::V4(_)) => "MATCH_IPV4_SOURCE",
+                    (false, IpNet::V4(_)) => "MATCH_IPV4_DEST",
+                    (true, IpNet::V6(_)) => "MATCH_IPV6_SOURCE",
+                    (false, IpNet::V6(_)) => "MATCH_IPV6_DEST",
+                };
+                json!({ "type": kind, "ip": net.to_string() })
+            }
+            "iptos" => {
+                let mask = self.small_number("TOS mask", 255)?;
+                let (start, end) = self.range("TOS range", 255)?;
+                json!({ "type": "MATCH_IP_TOS", "mask": mask, "start": start, "end": end })
+            }
+            "ipprotocol" => {
+                json!({ "type": "MATCH_IP_PROTOCOL", "ipProtocol": self.named("IP protocol", PROTOCOLS)? })
+            }
+            "ethertype" => {
+                json!({ "type": "MATCH_ETHERTYPE", "etherType": self.named("ethertype", ETHERTYPES)? })
+            }
+            "icmp" => {
+                let icmp_type = self.small_number("ICMP type", 255)?;
+                let code = match self.peek() {
+                    Some(token) if token.text == "-" => {
+                        self.position += 1;
+                        Value::Null
+                    }
+                    _ => json!(self.small_number("ICMP code or `-`", 255)?),
+                };
+                json!({ "type": "MATCH_ICMP", "icmpType": icmp_type, "icmpCode": code })
+            }
+            "sport" | "dport" => {
+                let (start, end) = self.range("port range", 65535)?;
+                let kind = if keyword == "sport" {
+                    "MATCH_IP_SOURCE_PORT_RANGE"
+                } else {
+                    "MATCH_IP_DEST_PORT_RANGE"
+                };
+                json!({ "type": kind, "start": start, "end": end })
+            }
+            "framesize" => {
+                let (start, end) = self.range("frame size range", 65535)?;
+                json!({ "type": "MATCH_FRAME_SIZE_RANGE", "start": start, "end": end })
+            }
+            "chr" => {
+                let token = self.next("characteristics")?;
+                let mut mask = 0u64;
+                for name in token.text.split(',') {
+                    mask |= lookup(CHARACTERISTICS, name)
+                        .or_else(|| parse_number(name))
+                        .ok_or_else(|| {
+                            Self::error(&token, format!("unknown characteristic `{}`", name))
+                        })?;
+                }
+                json!({ "type": "MATCH_CHARACTERISTICS", "mask": format!("{:016x}", mask) })
+            }
+            "random" => {
+                let token = self.next("a probability")?;
+                let probability = token
+                    .text
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| (0.0..=1.0).contains(x))
+                    .ok_or_else(|| {
+                        Self::error(
+                            &token,
+                            format!("expected a probability from 0 to 1, found `{}`", token.text),
+                        )
+                    })?;
+                json!({ "type": "MATCH_RANDOM", "probability": (probability * u32::MAX as f64) as u32 })
+            }
+            _ => match TAG_MATCHES.iter().find(|x| x.0 == keyword) {
+                Some((_, kind)) => {
+                    let tag = self.tag_reference()?;
+                    let id = match tag {
+                        Some(tag) => self.tags[tag].id as u64,
+                        None => self.small_number("tag id", u32::MAX as u64)?,
+                    };
+                    let value = self.tag_value(tag)?;
+                    json!({ "type": kind, "id": id, "value": value })
+                }
+                None => {
+                    return Err(Self::error(
+                        &self.tokens[index],
+                        format!("unknown match `{}`", keyword),
+                    ));
+                }
+            },
+        };
+
+        Ok(rule)
+    }
+}
+
+/// Compiles rules source into rules, tag and capability definitions.
+pub fn compile(source: &str) -> Result<Program, CompileError> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        position: 0,
+        tags: Vec::new(),
+        capabilities: Vec::new(),
+    };
+    let rules = parser.program()?;
+
+    Ok(Program {
+        rules,
+        tags: parser.tags,
+        capabilities: parser.capabilities,
+    })
+}
+
+fn u64_field(rule: &Value, name: &str) -> u64 {
+    match &rule[name] {
+        Value::String(s) => u64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap_or(0),
+        value => value.as_u64().unwrap_or(0),
+    }
+}
+
+fn str_field<'a>(rule: &'a Value, name: &str) -> &'a str {
+    rule[name].as_str().unwrap_or("?")
+}
+
+fn range(rule: &Value) -> String {
+    let (start, end) = (u64_field(rule, "start"), u64_field(rule, "end"));
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{}-{}", start, end)
+    }
+}
+
+fn decompile_action(rule: &Value) -> Option<String> {
+    let action = match rule["type"].as_str()? {
+        "ACTION_DROP" => "drop".to_string(),
+        "ACTION_ACCEPT" => "accept".to_string(),
+        "ACTION_BREAK" => "break".to_string(),
+        "ACTION_DEBUG_LOG" => "debug".to_string(),
+        "ACTION_TEE" => format!(
+            "tee {} {}",
+            u64_field(rule, "length"),
+            str_field(rule, "address")
+        ),
+        "ACTION_WATCH" => format!(
+            "watch {} {}",
+            u64_field(rule, "length"),
+            str_field(rule, "address")
+        ),
+        "ACTION_REDIRECT" => format!("redirect {}", str_field(rule, "address")),
+        _ => return None,
+    };
+    Some(action)
+}
+
+fn decompile_match(rule: &Value, tags: &[TagDefinition]) -> String {
+    let kind = rule["type"].as_str().unwrap_or("?");
+    match kind {
+        "MATCH_SOURCE_ZEROTIER_ADDRESS" => format!("ztsrc {}", str_field(rule, "zt")),
+        "MATCH_DEST_ZEROTIER_ADDRESS" => format!("ztdest {}", str_field(rule, "zt")),
+        "MATCH_VLAN_ID" => format!("vlan {}", u64_field(rule, "vlanId")),
+        "MATCH_VLAN_PCP" => format!("vlanpcp {}", u64_field(rule, "vlanPcp")),
+        "MATCH_VLAN_DEI" => format!("vlandei {}", u64_field(rule, "vlanDei")),
+        "MATCH_MAC_SOURCE" => format!("macsrc {}", str_field(rule, "mac")),
+        "MATCH_MAC_DEST" => format!("macdest {}", str_field(rule, "mac")),
+        "MATCH_IPV4_SOURCE" | "MATCH_IPV6_SOURCE" => format!("ipsrc {}", str_field(rule, "ip")),
+        "MATCH_IPV4_DEST" | "MATCH_IPV6_DEST" => format!("ipdest {}", str_field(rule, "ip")),
+        "MATCH_IP_TOS" => format!("iptos {} {}", u64_field(rule, "mask"), range(rule)),
+        "MATCH_IP_PROTOCOL" => {
+            let protocol = u64_field(rule, "ipProtocol");
+            match reverse_lookup(PROTOCOLS, protocol) {
+                Some(name) => format!("ipprotocol {}", name),
+                None => format!("ipprotocol {}", protocol),
+            }
+        }
+        "MATCH_ETHERTYPE" => {
+            let ethertype = u64_field(rule, "etherType");
+            match reverse_lookup(ETHERTYPES, ethertype) {
+                Some(name) => format!("ethertype {}", name),
+                None => format!("ethertype 0x{:04x}", ethertype),
+            }
+        }
+        "MATCH_ICMP" => format!(
+            "icmp {} {}",
+            u64_field(rule, "icmpType"),
+            rule["icmpCode"]
+                .as_u64()
+                .map(|x| x.to_string())
+                .unwrap_or("-".to_string())
+        ),
+        "MATCH_IP_SOURCE_PORT_RANGE" => format!("sport {}", range(rule)),
+        "MATCH_IP_DEST_PORT_RANGE" => format!("dport {}", range(rule)),
+        "MATCH_FRAME_SIZE_RANGE" => format!("framesize {}", range(rule)),
+        "MATCH_CHARACTERISTICS" => {
+            let mask = u64_field(rule, "mask");
+            let mut names = CHARACTERISTICS
+                .iter()
+                .filter(|x| mask & x.1 != 0)
+                .map(|x| x.0.to_string())
+                .collect::<Vec<String>>();
+            let known = CHARACTERISTICS.iter().fold(0, |acc, x| acc | x.1);
+            if mask & !known != 0 {
+                names.push(format!("0x{:x}", mask & !known));
+            }
+            format!("chr {}", names.join(","))
+        }
+        "MATCH_RANDOM" => format!(
+            "random {:.6}",
+            u64_field(rule, "probability") as f64 / u32::MAX as f64
+        ),
+        _ => match TAG_MATCHES.iter().find(|x| x.1 == kind) {
+            Some((keyword, _)) => {
+                let id = u64_field(rule, "id");
+                let value = u64_field(rule, "value");
+                let tag = tags.iter().find(|x| x.id as u64 == id);
+                let value = tag
+                    .and_then(|tag| {
+                        tag.enums
+                            .iter()
+                            .find(|x| *x.1 as u64 == value)
+                            .or_else(|| {
+                                tag.flags.iter().find(|x| {
+                                    ["tand", "tor", "txor"].contains(keyword)
+                                        && 1u64 << x.1 == value
+                                })
+                            })
+                            .map(|x| x.0.clone())
+                    })
+                    .unwrap_or(value.to_string());
+                match tag {
+                    Some(tag) => format!("{} {} {}", keyword, tag.name, value),
+                    None => format!("{} {} {}", keyword, id, value),
+                }
+            }
+            None => format!("# unsupported {}", rule),
+        },
+    }
+}
+
+/// Renders rules as one statement per line, e.g. `drop not ethertype ipv4;`.
+fn decompile_rules(rules: &[Value], tags: &[TagDefinition], indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut matches: Vec<String> = Vec::new();
+
+    for rule in rules {
+        if let Some(action) = decompile_action(rule) {
+            let mut statement = action;
+            for clause in matches.drain(..) {
+                statement.push(' ');
+                statement.push_str(&clause);
+            }
+            lines.push(format!("{}{};", indent, statement));
+            continue;
+        }
+
+        let mut clause = String::new();
+        if rule["or"].as_bool() == Some(true) {
+            clause.push_str("or ");
+        } else if !matches.is_empty() {
+            clause.push_str("and ");
+        }
+        if rule["not"].as_bool() == Some(true) {
+            clause.push_str("not ");
+        }
+        clause.push_str(&decompile_match(rule, tags));
+        matches.push(clause);
+    }
+
+    if !matches.is_empty() {
+        lines.push(format!(
+            "{}# matches without an action: {}",
+            indent,
+            matches.join(" ")
+        ));
+    }
+    lines
+}
+
+/// Renders a program back to rules source.
+pub fn decompile(program: &Program) -> String {
+    let mut lines = Vec::new();
+
+    for tag in &program.tags {
+        lines.push(format!("tag {}", tag.name));
+        lines.push(format!("  id {}", tag.id));
+        let mut enums = tag.enums.iter().collect::<Vec<(&String, &u32)>>();
+        enums.sort_by_key(|x| x.1);
+        for (name, value) in enums {
+            lines.push(format!("  enum {} {}", value, name));
+        }
+        let mut flags = tag.flags.iter().collect::<Vec<(&String, &u32)>>();
+        flags.sort_by_key(|x| x.1);
+        for (name, bit) in flags {
+            lines.push(format!("  flag {} {}", bit, name));
+        }
+        if let Some(default) = tag.default {
+            match tag.enums.iter().find(|x| *x.1 == default) {
+                Some((name, _)) => lines.push(format!("  default {}", name)),
+                None => lines.push(format!("  default {}", default)),
+            }
+        }
+        lines.push(";".to_string());
+    }
+
+    for capability in &program.capabilities {
+        lines.push(format!("cap {}", capability.name));
+        lines.push(format!("  id {}", capability.id));
+        if capability.default {
+            lines.push("  default".to_string());
+        }
+        lines.extend(decompile_rules(&capability.rules, &program.tags, "  "));
+        lines.push(";".to_string());
+    }
+
+    lines.extend(decompile_rules(&program.rules, &program.tags, ""));
+    lines.join("\n")
+}
+
+/// Source to edit for a network, `rulesSource` when it is up to date or decompiled rules.
+pub fn source(network: &NetworkResponse) -> String {
+    match Program::from_source(network) {
+        Some(_) => network.rules_source.clone(),
+        None => decompile(&Program::from_network(network)),
+    }
+}
+
+/// Decompiled rules for the network Info view.
+pub fn summary(program: &Program) -> String {
+    if program.rules.is_empty() {
+        return "None (all traffic is dropped)".to_string();
+    }
+    decompile(program)
+        .lines()
+        .map(|x| format!("\n  {}", x))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dto::MemberResponse;
+
+    const SOURCE: &str = "\
+# Only IPv4 and IPv6 from a known MAC
+drop
+  not ethertype ipv4
+  and not ethertype ipv6
+;
+drop macsrc 0123456789AB chr inbound;
+
+tag department
+  id 1000
+  enum 100 sales
+  enum 200 engineering
+  flag 3 remote
+  default sales
+;
+
+cap superuser
+  id 2000
+  accept;
+;
+
+accept teq department engineering;
+";
+
+    /// Network as the controller returns it before any rules were set.
+    pub(crate) fn empty_network() -> NetworkResponse {
+        serde_json::from_value(json!({
+            "id": "8056c2e21c000001",
+            "v4AssignMode": { "zt": false },
+            "v6AssignMode": { "zt": false, "6plane": false, "rfc4193": false },
+            "creationTime": 0,
+            "private": true,
+            "enableBroadcast": true,
+            "mtu": 2800,
+            "multicastLimit": 32,
+            "routes": [],
+            "ipAssignmentPools": [],
+            "dns": [],
+            "rules": [{ "type": "ACTION_ACCEPT", "not": false, "or": false }]
+        }))
+        .unwrap()
+    }
+
+    /// Unauthorized member of `empty_network` as the controller returns it.
+    pub(crate) fn member(id: &str, ips: &[&str]) -> MemberResponse {
+        serde_json::from_value(json!({
+            "id": id,
+            "nwid": "8056c2e21c000001",
+            "authorized": false,
+            "authenticationExpiryTime": 0,
+            "creationTime": 0,
+            "lastAuthorizedTime": 0,
+            "lastDeauthorizedTime": 0,
+            "ipAssignments": ips
+        }))
+        .unwrap()
+    }
+
+    /// `network` as the controller returns it after it was posted, with `not` and `or`
+    /// on every rule.
+    pub(crate) fn stored(mut network: NetworkResponse) -> NetworkResponse {
+        let rules = network
+            .rules
+            .iter_mut()
+            .chain(network.capabilities.iter_mut().flat_map(|x| &mut x.rules));
+        for rule in rules {
+            let object = rule.as_object_mut().unwrap();
+            object.entry("not").or_insert(json!(false));
+            object.entry("or").or_insert(json!(false));
+        }
+        network
+    }
+
+    /// Network as the controller returns it after `SOURCE` was posted.
+    fn controller_network() -> NetworkResponse {
+        NetworkResponse {
+            name: Some("office".to_string()),
+            rules: serde_json::from_value(json!([
+                { "type": "MATCH_ETHERTYPE", "etherType": 2048, "not": true, "or": false },
+                { "type": "MATCH_ETHERTYPE", "etherType": 34525, "not": true, "or": false },
+                { "type": "ACTION_DROP", "not": false, "or": false },
+                { "type": "MATCH_MAC_SOURCE", "mac": "01:23:45:67:89:ab", "not": false, "or": false },
+                { "type": "MATCH_CHARACTERISTICS", "mask": "8000000000000000", "not": false, "or": false },
+                { "type": "ACTION_DROP", "not": false, "or": false },
+                { "type": "MATCH_TAGS_EQUAL", "id": 1000, "value": 200, "not": false, "or": false },
+                { "type": "ACTION_ACCEPT", "not": false, "or": false }
+            ]))
+            .unwrap(),
+            tags: vec![NetworkTag {
+                id: 1000,
+                default: Some(100),
+            }],
+            capabilities: vec![NetworkCapability {
+                id: 2000,
+                default: false,
+                rules: vec![json!({ "type": "ACTION_ACCEPT", "not": false, "or": false })],
+            }],
+            rules_source: SOURCE.to_string(),
+            ..empty_network()
+        }
+    }
+
+    #[test]
+    fn compiles_to_controller_rules() {
+        let program = compile(SOURCE).unwrap();
+        let network = controller_network();
+        assert_eq!(program.rules, network.rules);
+        assert_eq!(program.capabilities[0].rules, network.capabilities[0].rules);
+    }
+
+    #[test]
+    fn recovers_names_from_controller_rules() {
+        let network = controller_network();
+        let program = Program::from_network(&network);
+
+        let department = program.tag("department").unwrap();
+        assert_eq!(department.id, 1000);
+        assert_eq!(department.value("engineering"), Some(200));
+        assert_eq!(department.value("remote"), Some(8));
+        assert_eq!(program.capability("superuser").unwrap().id, 2000);
+        assert_eq!(source(&network), SOURCE);
+    }
+
+    #[test]
+    fn recovers_names_from_rules_without_not_and_or() {
+        let mut network = controller_network();
+        for rule in &mut network.rules {
+            let object = rule.as_object_mut().unwrap();
+            if object["type"].as_str().unwrap().starts_with("ACTION_") {
+                object.remove("not");
+                object.remove("or");
+            }
+        }
+        network.rules[3]["mac"] = json!("0123456789ab");
+
+        assert!(Program::from_network(&network).tag("department").is_some());
+    }
+
+    #[test]
+    fn round_trips_through_the_controller() {
+        let program = compile(SOURCE).unwrap();
+        let network = stored(NetworkResponse {
+            rules: program.rules.clone(),
+            tags: program.network_tags(),
+            capabilities: program.network_capabilities(),
+            rules_source: SOURCE.to_string(),
+            ..empty_network()
+        });
+
+        let recovered = Program::from_network(&network);
+        assert!(recovered.tag("department").is_some());
+
+        // Decompiled source compiles back to the same rules
+        let decompiled = compile(&decompile(&recovered)).unwrap();
+        assert_eq!(decompiled.rules, program.rules);
+        assert_eq!(decompiled.tags[0].enums, program.tags[0].enums);
+    }
+
+    #[test]
+    fn falls_back_to_ids_for_a_stale_source() {
+        let mut network = controller_network();
+        network.rules.pop();
+        network.rules.pop();
+        network.rules.pop();
+
+        let program = Program::from_network(&network);
+        assert!(program.tag("department").is_none());
+        assert!(program.tag("tag_1000").is_some());
+        assert!(program.capability("cap_2000").is_some());
+        assert_ne!(source(&network), SOURCE);
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = compile("accept;\ndrop\n  not ethertype foo;").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 17));
+        assert_eq!(error.message, "unknown ethertype `foo`");
+
+        let error = compile("drop").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 5));
+        assert_eq!(
+            error.message,
+            "expected `;` to end the rule, found end of input"
+        );
+
+        let error = compile("accept teq department 1;").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 12));
+
+        let error = compile("tag big\n  id 1\n  default 4294967296\n;").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 11));
+    }
+
+    #[test]
+    fn round_trips_sender_and_receiver_tag_matches() {
+        let source = "\
+tag role
+  id 5
+  enum 1 admin
+;
+accept tseq role admin;
+drop treq 7 3;";
+        let program = compile(source).unwrap();
+        assert_eq!(
+            program.rules[0],
+            json!({ "type": "MATCH_TAG_SENDER", "id": 5, "value": 1, "not": false, "or": false })
+        );
+        assert_eq!(
+            program.rules[2],
+            json!({ "type": "MATCH_TAG_RECEIVER", "id": 7, "value": 3, "not": false, "or": false })
+        );
+
+        let decompiled = decompile(&program);
+        assert!(decompiled.contains("accept tseq role admin;"));
+        assert!(decompiled.contains("drop treq 7 3;"));
+        assert_eq!(compile(&decompiled).unwrap().rules, program.rules);
+    }
+
+    #[test]
+    fn combines_flags_with_or() {
+        let program = compile("tag t id 1 flag 0 a flag 31 b enum 7 seven;").unwrap();
+        let tag = program.tag("t").unwrap();
+        assert_eq!(tag.value("a,a"), Some(1));
+        assert_eq!(tag.value("a,b"), Some(0x80000001));
+        assert_eq!(tag.value("b,b"), Some(0x80000000));
+        assert_eq!(tag.value("seven"), Some(7));
+        assert_eq!(tag.value("a,c"), None);
+    }
+}
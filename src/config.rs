@@ -0,0 +1,106 @@
+use crate::output::OutputFormat;
+use crate::token;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+pub const DEFAULT_URL: &str = "http://localhost:9993";
+
+/// Contents of `config.toml`.
+///
+/// ```toml
+/// default_profile = "lab"
+///
+/// [profiles.lab]
+/// url = "http://localhost:9993"
+/// token_file = "/var/lib/zerotier-one/authtoken.secret"
+/// default_network = "8056c2e21c000001"
+/// output = "table"
+/// color = true
+/// ```
+#[derive(Deserialize, Default)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct Profile {
+    pub url: Option<String>,
+    pub token: Option<String>,
+    /// File containing the token, used when `token` is not set
+    pub token_file: Option<PathBuf>,
+    pub default_network: Option<String>,
+    pub output: Option<OutputFormat>,
+    pub color: Option<bool>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/zerotier-manager/config.toml` or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|x| x.join("zerotier-manager").join("config.toml"))
+    }
+
+    /// Loads the config from `path`, or from the default location if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?;
+        toml::from_str(&content)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e).into())
+    }
+
+    /// Returns the named profile, the `default_profile` or an empty profile.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, Box<dyn Error>> {
+        match name.or(self.default_profile.as_deref()) {
+            Some(name) => {
+                self.profiles.get(name).cloned().ok_or_else(|| {
+                    format!("Profile `{}` is not defined in the config", name).into()
+                })
+            }
+            None => Ok(Profile::default()),
+        }
+    }
+}
+
+impl Profile {
+    /// `URL` env var, then the profile, then the default local controller.
+    pub fn url(&self) -> String {
+        env::var("URL")
+            .ok()
+            .or_else(|| self.url.clone())
+            .unwrap_or(DEFAULT_URL.to_string())
+    }
+
+    /// `TOKEN` env var unless empty, then the profile token, then the profile token file.
+    ///
+    /// For a controller on this machine the service `authtoken.secret` is used as a last resort.
+    pub fn token(&self) -> Result<String, Box<dyn Error>> {
+        if let Some(token) = env::var("TOKEN").ok().filter(|x| !x.is_empty()) {
+            return Ok(token);
+        }
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+        if let Some(path) = &self.token_file {
+            return token::read(path);
+        }
+        if token::is_local(&self.url()) {
+            return token::discover();
+        }
+        Err(
+            "No API token configured, set TOKEN, pass --token-file or add a token to the profile"
+                .into(),
+        )
+    }
+}
@@ -0,0 +1,516 @@
+use crate::api::{APIClient, EditMember, EditNetwork};
+use crate::backup::NetworkBackup;
+use crate::compiler::{self, Program};
+use crate::diff::{self, FieldChange};
+use crate::dto::{
+    MemberResponse, MemberTag, NetworkDNS, NetworkIPAssignmentPool, NetworkResponse, NetworkRoute,
+    NetworkV4AssignMode, NetworkV6AssignMode,
+};
+use crate::error::Error;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Desired-state file, networks and members by ID.
+///
+/// Settings that are left out are not managed and keep their live value.
+///
+/// ```yaml
+/// networks:
+///   8056c2e21c000001:
+///     name: office
+///     pools: [10.0.0.1-10.0.0.254]
+///     routes: [10.0.0.0/24]
+///     rules: |
+///       accept;
+///     members:
+///       a1b2c3d4e5:
+///         name: laptop
+///         authorized: true
+///         ips: [10.0.0.10]
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DesiredState {
+    #[serde(default)]
+    pub networks: BTreeMap<String, DesiredNetwork>,
+    /// Delete networks of the controller that are not in the file
+    #[serde(default)]
+    pub prune_networks: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DesiredNetwork {
+    pub name: Option<String>,
+    pub private: Option<bool>,
+    pub broadcast: Option<bool>,
+    pub mtu: Option<u32>,
+    pub multicast_limit: Option<u32>,
+    pub v4_zt: Option<bool>,
+    pub v6_zt: Option<bool>,
+    pub v6_6plane: Option<bool>,
+    pub v6_rfc4193: Option<bool>,
+    /// Pools as `START-END`
+    pub pools: Option<Vec<String>>,
+    /// Routes as `TARGET[@VIA]`
+    pub routes: Option<Vec<String>>,
+    pub dns: Option<NetworkDNS>,
+    /// Rules language source
+    pub rules: Option<String>,
+    #[serde(default)]
+    pub members: BTreeMap<String, DesiredMember>,
+    /// Delete members of the network that are not in the file
+    #[serde(default)]
+    pub prune_members: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DesiredMember {
+    pub authorized: Option<bool>,
+    pub name: Option<String>,
+    pub ips: Option<Vec<IpAddr>>,
+    /// Tag values by tag name from the network rules, replaces all tags
+    pub tags: Option<BTreeMap<String, TagValue>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum TagValue {
+    Number(u32),
+    Name(String),
+}
+
+impl DesiredState {
+    /// Reads a YAML (or JSON) desired-state file.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid desired state {}: {}", path.display(), e).into())
+    }
+}
+
+/// Settings the controller gives a network created without any.
+fn new_network(id: &str) -> NetworkResponse {
+    NetworkResponse {
+        id: id.to_string(),
+        name: None,
+        v4_assign_mode: NetworkV4AssignMode { zt: false },
+        v6_assign_mode: NetworkV6AssignMode {
+            zt: false,
+            six_plane: false,
+            rfc4193: false,
+        },
+        creation_time: 0,
+        private: true,
+        enable_broadcast: true,
+        mtu: 2800,
+        multicast_limit: 32,
+        remote_trace_target: None,
+        remote_trace_level: 0,
+        routes: Vec::new(),
+        ip_assignment_pools: Vec::new(),
+        dns: NetworkDNS::default(),
+        rules: vec![json!({"type": "ACTION_ACCEPT", "not": false, "or": false})],
+        capabilities: Vec::new(),
+        tags: Vec::new(),
+        rules_source: String::new(),
+    }
+}
+
+fn new_member(nwid: &str, id: &str) -> MemberResponse {
+    MemberResponse {
+        id: id.to_string(),
+        nwid: nwid.to_string(),
+        name: None,
+        authorized: false,
+        authentication_expiry_time: 0,
+        creation_time: 0,
+        last_authorized_time: 0,
+        last_deauthorized_time: 0,
+        ip_assignments: Vec::new(),
+        tags: Vec::new(),
+        capabilities: Vec::new(),
+        revision: 0,
+        active_bridge: false,
+        no_auto_assign_ips: false,
+        sso_exempt: false,
+        v_major: -1,
+        v_minor: -1,
+        v_rev: -1,
+        v_proto: -1,
+    }
+}
+
+impl DesiredNetwork {
+    /// `network` with the managed settings replaced by the desired ones.
+    fn merge(&self, network: &NetworkResponse) -> Result<NetworkResponse, String> {
+        let mut network = network.clone();
+        let error = |e: String| format!("network {}: {}", network.id, e);
+
+        if let Some(name) = &self.name {
+            network.name = Some(name.clone());
+        }
+        if let Some(private) = self.private {
+            network.private = private;
+        }
+        if let Some(broadcast) = self.broadcast {
+            network.enable_broadcast = broadcast;
+        }
+        if let Some(mtu) = self.mtu {
+            network.mtu = mtu;
+        }
+        if let Some(limit) = self.multicast_limit {
+            network.multicast_limit = limit;
+        }
+        if let Some(zt) = self.v4_zt {
+            network.v4_assign_mode.zt = zt;
+        }
+        if let Some(zt) = self.v6_zt {
+            network.v6_assign_mode.zt = zt;
+        }
+        if let Some(six_plane) = self.v6_6plane {
+            network.v6_assign_mode.six_plane = six_plane;
+        }
+        if let Some(rfc4193) = self.v6_rfc4193 {
+            network.v6_assign_mode.rfc4193 = rfc4193;
+        }
+        if let Some(pools) = &self.pools {
+            network.ip_assignment_pools = pools
+                .iter()
+                .map(|x| x.parse::<NetworkIPAssignmentPool>())
+                .collect::<Result<_, _>>()
+                .map_err(error)?;
+        }
+        if let Some(routes) = &self.routes {
+            network.routes = routes
+                .iter()
+                .map(|x| x.parse::<NetworkRoute>())
+                .collect::<Result<_, _>>()
+                .map_err(error)?;
+        }
+        if let Some(dns) = &self.dns {
+            network.dns = dns.clone();
+        }
+        if let Some(source) = &self.rules {
+            let program = compiler::compile(source).map_err(|e| error(e.to_string()))?;
+            network.capabilities = program.network_capabilities();
+            network.tags = program.network_tags();
+            network.rules = program.rules;
+            network.rules_source = source.clone();
+        }
+        Ok(network)
+    }
+}
+
+impl DesiredMember {
+    /// `member` with the managed fields replaced, tags are looked up in `program`.
+    fn merge(&self, member: &MemberResponse, program: &Program) -> Result<MemberResponse, String> {
+        let mut member = member.clone();
+
+        if let Some(authorized) = self.authorized {
+            member.authorized = authorized;
+        }
+        if let Some(name) = &self.name {
+            member.name = Some(name.clone());
+        }
+        if let Some(ips) = &self.ips {
+            member.ip_assignments = ips.iter().map(|x| x.to_string()).collect();
+        }
+        if let Some(tags) = &self.tags {
+            member.tags = Vec::new();
+            for (name, value) in tags {
+                let error = || format!("member {}: invalid value for tag `{}`", member.id, name);
+                let definition = program.tag(name).ok_or_else(|| {
+                    format!(
+                        "member {}: tag `{}` is not defined in the network rules",
+                        member.id, name
+                    )
+                })?;
+                let value = match value {
+                    TagValue::Number(value) => *value,
+                    TagValue::Name(value) => definition.value(value).ok_or_else(error)?,
+                };
+                member.tags.push(MemberTag(definition.id, value));
+            }
+        }
+        Ok(member)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ActionKind {
+    Create,
+    Update,
+    Delete,
+}
+
+pub enum Resource {
+    Network(NetworkResponse),
+    Member(MemberResponse),
+}
+
+/// One call to the controller, with the fields it changes.
+pub struct Action {
+    pub kind: ActionKind,
+    pub resource: Resource,
+    pub changes: Vec<FieldChange>,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ActionKind::Create => "+",
+            ActionKind::Update => "~",
+            ActionKind::Delete => "-",
+        };
+        match &self.resource {
+            Resource::Network(network) => write!(f, "{} network {}", kind, network)?,
+            Resource::Member(member) => match &member.name {
+                Some(name) => write!(
+                    f,
+                    "{} member {} ({}) of {}",
+                    kind, name, member.id, member.nwid
+                )?,
+                None => write!(f, "{} member {} of {}", kind, member.id, member.nwid)?,
+            },
+        }
+        for change in &self.changes {
+            write!(f, "\n    {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+impl Action {
+    pub async fn apply(&self, client: &APIClient) -> Result<(), Error> {
+        match (&self.resource, self.kind) {
+            (Resource::Network(network), ActionKind::Delete) => {
+                client.delete_network(&network.id).await
+            }
+            (Resource::Network(network), _) => {
+                client
+                    .edit_network(&network.id, EditNetwork::from(network))
+                    .await
+            }
+            (Resource::Member(member), ActionKind::Delete) => {
+                client.delete_member(&member.nwid, &member.id).await
+            }
+            (Resource::Member(member), _) => {
+                client
+                    .edit_member(&member.nwid, &member.id, EditMember::from(member))
+                    .await
+            }
+        }
+    }
+}
+
+/// Calls that bring the `live` networks and members to the desired state.
+///
+/// Unchanged networks and members are left out; networks come before their members
+/// and deletions of networks come last.
+pub fn plan(desired: &DesiredState, live: &[NetworkBackup]) -> Result<Vec<Action>, String> {
+    let mut actions = Vec::new();
+
+    for (id, desired_network) in &desired.networks {
+        let current = live.iter().find(|x| x.network.id == *id);
+        let (kind, base) = match current {
+            Some(current) => (ActionKind::Update, current.network.clone()),
+            None => (ActionKind::Create, new_network(id)),
+        };
+        let network = desired_network.merge(&base)?;
+        let changes = diff::network(&base, &network);
+        let program = Program::from_network(&network);
+
+        if kind == ActionKind::Create || !changes.is_empty() {
+            actions.push(Action {
+                kind,
+                resource: Resource::Network(network),
+                changes,
+            });
+        }
+
+        let members = current.map(|x| x.members.as_slice()).unwrap_or_default();
+        for (member_id, desired_member) in &desired_network.members {
+            let current = members.iter().find(|x| x.id == *member_id);
+            let (kind, base) = match current {
+                Some(current) => (ActionKind::Update, current.clone()),
+                None => (ActionKind::Create, new_member(id, member_id)),
+            };
+            let member = desired_member.merge(&base, &program)?;
+            let changes = diff::member(&base, &member, &program);
+            if kind == ActionKind::Create || !changes.is_empty() {
+                actions.push(Action {
+                    kind,
+                    resource: Resource::Member(member),
+                    changes,
+                });
+            }
+        }
+        if desired_network.prune_members {
+            actions.extend(
+                members
+                    .iter()
+                    .filter(|x| !desired_network.members.contains_key(&x.id))
+                    .map(|x| Action {
+                        kind: ActionKind::Delete,
+                        resource: Resource::Member(x.clone()),
+                        changes: Vec::new(),
+                    }),
+            );
+        }
+    }
+
+    if desired.prune_networks {
+        actions.extend(
+            live.iter()
+                .filter(|x| !desired.networks.contains_key(&x.network.id))
+                .map(|x| Action {
+                    kind: ActionKind::Delete,
+                    resource: Resource::Network(x.network.clone()),
+                    changes: Vec::new(),
+                }),
+        );
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::compiler::tests::{empty_network, member, stored};
+
+    pub(crate) const DESIRED: &str = "
+networks:
+  8056c2e21c000001:
+    name: office
+    rules: |
+      # Engineering may talk to everyone
+      tag department
+        id 1000
+        enum 100 sales
+        enum 200 engineering
+        default sales
+      ;
+      cap superuser
+        id 2000
+        accept;
+      ;
+      accept;
+    members:
+      1111111111:
+        name: laptop
+        authorized: true
+        tags:
+          department: engineering
+";
+
+    /// Live state before the first apply, a network with the default rules and one member.
+    pub(crate) fn live() -> Vec<NetworkBackup> {
+        vec![NetworkBackup {
+            network: empty_network(),
+            members: vec![member("1111111111", &[])],
+        }]
+    }
+
+    /// Live state after the controller carried out `actions`.
+    pub(crate) fn apply(live: &mut Vec<NetworkBackup>, actions: Vec<Action>) {
+        for action in actions {
+            match (action.resource, action.kind) {
+                (Resource::Network(network), ActionKind::Delete) => {
+                    live.retain(|x| x.network.id != network.id)
+                }
+                (Resource::Network(network), _) => {
+                    let network = stored(network);
+                    match live.iter_mut().find(|x| x.network.id == network.id) {
+                        Some(current) => current.network = network,
+                        None => live.push(NetworkBackup {
+                            network,
+                            members: Vec::new(),
+                        }),
+                    }
+                }
+                (Resource::Member(member), kind) => {
+                    let current = live.iter_mut().find(|x| x.network.id == member.nwid);
+                    let members = &mut current.unwrap().members;
+                    members.retain(|x| x.id != member.id);
+                    if kind != ActionKind::Delete {
+                        members.push(member);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn plan_is_empty_after_apply() {
+        let desired: DesiredState = serde_yaml::from_str(DESIRED).unwrap();
+        let mut live = live();
+
+        let actions = plan(&desired, &live).unwrap();
+        assert_eq!(actions.len(), 2);
+        apply(&mut live, actions);
+
+        let actions = plan(&desired, &live).unwrap();
+        let pending = actions.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert!(pending.is_empty(), "{:?}", pending);
+        assert_eq!(live[0].members[0].tags, vec![MemberTag(1000, 200)]);
+    }
+
+    #[test]
+    fn resolves_tag_names_from_live_rules() {
+        let desired: DesiredState = serde_yaml::from_str(DESIRED).unwrap();
+        let mut live = live();
+        let actions = plan(&desired, &live).unwrap();
+        apply(&mut live, actions);
+
+        // Without `rules:` the names come from the live rulesSource
+        let desired: DesiredState = serde_yaml::from_str(
+            "
+networks:
+  8056c2e21c000001:
+    members:
+      1111111111:
+        tags:
+          department: sales
+",
+        )
+        .unwrap();
+        let actions = plan(&desired, &live).unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(
+            actions[0].changes[0].to_string(),
+            "tag: - department=engineering"
+        );
+        assert_eq!(actions[0].changes[1].to_string(), "tag: + department=sales");
+    }
+
+    #[test]
+    fn names_both_sides_of_a_rule_change_after_the_desired_rules() {
+        let desired: DesiredState = serde_yaml::from_str(DESIRED).unwrap();
+        let mut live = live();
+        let actions = plan(&desired, &live).unwrap();
+        apply(&mut live, actions);
+        // Rules uploaded as JSON leave no source to take the names from
+        live[0].network.rules_source = String::new();
+
+        let desired: DesiredState =
+            serde_yaml::from_str(&DESIRED.replace("default sales", "default engineering")).unwrap();
+        let actions = plan(&desired, &live).unwrap();
+        let changes = actions[0]
+            .changes
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec!["rule: -   default sales", "rule: +   default engineering"]
+        );
+    }
+}
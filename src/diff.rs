@@ -0,0 +1,147 @@
+use crate::compiler::{self, Program};
+use crate::dto::{MemberResponse, NetworkResponse, remote_trace};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// One field that differs between two versions of a network or member.
+///
+/// List fields report each added or removed item separately, with the other side `None`.
+#[derive(Serialize, Debug)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "{}: {} → {}", self.field, old, new),
+            (None, Some(new)) => write!(f, "{}: + {}", self.field, new),
+            (Some(old), None) => write!(f, "{}: - {}", self.field, old),
+            (None, None) => write!(f, "{}", self.field),
+        }
+    }
+}
+
+struct Changes(Vec<FieldChange>);
+
+impl Changes {
+    fn value<T: PartialEq + Display>(&mut self, field: &'static str, old: T, new: T) {
+        if old != new {
+            self.0.push(FieldChange {
+                field,
+                old: Some(old.to_string()),
+                new: Some(new.to_string()),
+            });
+        }
+    }
+
+    fn list<T: Display>(&mut self, field: &'static str, old: &[T], new: &[T]) {
+        let old = old.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        let new = new.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        for item in old.iter().filter(|x| !new.contains(x)) {
+            self.0.push(FieldChange {
+                field,
+                old: Some(item.clone()),
+                new: None,
+            });
+        }
+        for item in new.iter().filter(|x| !old.contains(x)) {
+            self.0.push(FieldChange {
+                field,
+                old: None,
+                new: Some(item.clone()),
+            });
+        }
+    }
+}
+
+fn quoted(name: &Option<String>) -> String {
+    format!("\"{}\"", name.as_deref().unwrap_or_default())
+}
+
+/// Settings, pools, routes, DNS and rules that differ between two versions of a network.
+pub fn network(old: &NetworkResponse, new: &NetworkResponse) -> Vec<FieldChange> {
+    let mut changes = Changes(Vec::new());
+    changes.value("name", quoted(&old.name), quoted(&new.name));
+    changes.value("private", old.private, new.private);
+    changes.value("broadcast", old.enable_broadcast, new.enable_broadcast);
+    changes.value("MTU", old.mtu, new.mtu);
+    changes.value("multicast limit", old.multicast_limit, new.multicast_limit);
+    changes.value(
+        "remote trace",
+        remote_trace(&old.remote_trace_target, old.remote_trace_level),
+        remote_trace(&new.remote_trace_target, new.remote_trace_level),
+    );
+    changes.value("v4 ZT mode", old.v4_assign_mode.zt, new.v4_assign_mode.zt);
+    changes.value(
+        "v6 modes",
+        old.v6_assign_mode.to_string(),
+        new.v6_assign_mode.to_string(),
+    );
+    changes.list("pool", &old.ip_assignment_pools, &new.ip_assignment_pools);
+    changes.list("route", &old.routes, &new.routes);
+    changes.value("DNS domain", &old.dns.domain, &new.dns.domain);
+    changes.list("DNS server", &old.dns.servers, &new.dns.servers);
+
+    // Compare the compiled form, the source may differ only in comments and names
+    if !compiler::same_rules(old, new) {
+        // Both sides are named after the new definitions so renames do not show up as changes
+        let names = Program::from_network(new);
+        let old = compiler::decompile(&Program::named_after(old, &names));
+        let new = compiler::decompile(&Program::named_after(new, &names));
+        changes.list(
+            "rule",
+            &old.lines().collect::<Vec<&str>>(),
+            &new.lines().collect::<Vec<&str>>(),
+        );
+    }
+
+    changes.0
+}
+
+/// Authorization, name, IPs, tags, capabilities and flags that differ between two versions of a member.
+///
+/// Tags and capabilities are named after the definitions in `program`.
+pub fn member(old: &MemberResponse, new: &MemberResponse, program: &Program) -> Vec<FieldChange> {
+    let capability = |id: &u32| match program.capabilities.iter().find(|x| x.id == *id) {
+        Some(definition) => definition.name.clone(),
+        None => id.to_string(),
+    };
+
+    let mut changes = Changes(Vec::new());
+    changes.value("authorized", old.authorized, new.authorized);
+    changes.value("name", quoted(&old.name), quoted(&new.name));
+    changes.list("IP", &old.ip_assignments, &new.ip_assignments);
+    changes.list(
+        "tag",
+        &old.tags
+            .iter()
+            .map(|x| program.describe_tag(x))
+            .collect::<Vec<String>>(),
+        &new.tags
+            .iter()
+            .map(|x| program.describe_tag(x))
+            .collect::<Vec<String>>(),
+    );
+    changes.list(
+        "capability",
+        &old.capabilities
+            .iter()
+            .map(capability)
+            .collect::<Vec<String>>(),
+        &new.capabilities
+            .iter()
+            .map(capability)
+            .collect::<Vec<String>>(),
+    );
+    changes.value("active bridge", old.active_bridge, new.active_bridge);
+    changes.value(
+        "no auto-assign IPs",
+        old.no_auto_assign_ips,
+        new.no_auto_assign_ips,
+    );
+    changes.value("SSO exempt", old.sso_exempt, new.sso_exempt);
+    changes.0
+}
@@ -0,0 +1,203 @@
+use crate::backup::NetworkBackup;
+use crate::compiler::Program;
+use crate::desired::{self, ActionKind, DesiredState, Resource};
+use crate::diff::{self, FieldChange};
+use crate::output::Tabular;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// A live field that differs from the baseline, `old` is the baseline and `new` the live value.
+#[derive(Serialize, Debug)]
+pub struct Drift {
+    pub network: String,
+    pub member: Option<String>,
+    #[serde(flatten)]
+    pub change: FieldChange,
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.member {
+            Some(member) => write!(f, "{}/{} {}", self.network, member, self.change),
+            None => write!(f, "{} {}", self.network, self.change),
+        }
+    }
+}
+
+impl Tabular for Drift {
+    fn headers() -> Vec<&'static str> {
+        vec!["NETWORK", "MEMBER", "FIELD", "OLD", "NEW"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.network.clone(),
+            self.member.clone().unwrap_or_default(),
+            self.change.field.to_string(),
+            self.change.old.clone().unwrap_or_default(),
+            self.change.new.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn entries(network: &str, member: Option<&str>, changes: Vec<FieldChange>) -> Vec<Drift> {
+    changes
+        .into_iter()
+        .map(|change| Drift {
+            network: network.to_string(),
+            member: member.map(|x| x.to_string()),
+            change,
+        })
+        .collect()
+}
+
+/// A whole network or member that exists only in the baseline (`live` false) or only live.
+fn presence(field: &'static str, label: String, live: bool) -> FieldChange {
+    if live {
+        FieldChange {
+            field,
+            old: None,
+            new: Some(label),
+        }
+    } else {
+        FieldChange {
+            field,
+            old: Some(label),
+            new: None,
+        }
+    }
+}
+
+/// Differences of the live networks and members from a `backup` snapshot.
+pub fn from_snapshot(baseline: &[NetworkBackup], live: &[NetworkBackup]) -> Vec<Drift> {
+    let mut drift = Vec::new();
+
+    for old in baseline {
+        let Some(new) = live.iter().find(|x| x.network.id == old.network.id) else {
+            drift.extend(entries(
+                &old.network.id,
+                None,
+                vec![presence("network", old.network.to_string(), false)],
+            ));
+            continue;
+        };
+        drift.extend(entries(
+            &new.network.id,
+            None,
+            diff::network(&old.network, &new.network),
+        ));
+
+        let program = Program::from_network(&new.network);
+        for old_member in &old.members {
+            match new.members.iter().find(|x| x.id == old_member.id) {
+                Some(new_member) => drift.extend(entries(
+                    &new.network.id,
+                    Some(&new_member.id),
+                    diff::member(old_member, new_member, &program),
+                )),
+                None => drift.extend(entries(
+                    &new.network.id,
+                    None,
+                    vec![presence("member", old_member.to_string(), false)],
+                )),
+            }
+        }
+        for new_member in new
+            .members
+            .iter()
+            .filter(|x| !old.members.iter().any(|y| y.id == x.id))
+        {
+            drift.extend(entries(
+                &new.network.id,
+                None,
+                vec![presence("member", new_member.to_string(), true)],
+            ));
+        }
+    }
+
+    for new in live
+        .iter()
+        .filter(|x| !baseline.iter().any(|y| y.network.id == x.network.id))
+    {
+        drift.extend(entries(
+            &new.network.id,
+            None,
+            vec![presence("network", new.network.to_string(), true)],
+        ));
+    }
+    drift
+}
+
+/// Differences of the live networks and members from a desired-state file.
+///
+/// This is the plan read backwards: what `apply` would create is missing live,
+/// what it would delete was added live and updated fields changed live.
+pub fn from_desired(desired: &DesiredState, live: &[NetworkBackup]) -> Result<Vec<Drift>, String> {
+    let mut drift = Vec::new();
+
+    for action in desired::plan(desired, live)? {
+        let changes = match action.kind {
+            ActionKind::Update => action
+                .changes
+                .into_iter()
+                .map(|x| FieldChange {
+                    field: x.field,
+                    old: x.new,
+                    new: x.old,
+                })
+                .collect(),
+            kind => {
+                let (field, label) = match &action.resource {
+                    Resource::Network(network) => ("network", network.to_string()),
+                    Resource::Member(member) => ("member", member.to_string()),
+                };
+                vec![presence(field, label, kind == ActionKind::Delete)]
+            }
+        };
+        drift.extend(match &action.resource {
+            Resource::Member(member) if action.kind == ActionKind::Update => {
+                entries(&member.nwid, Some(&member.id), changes)
+            }
+            Resource::Member(member) => entries(&member.nwid, None, changes),
+            Resource::Network(network) => entries(&network.id, None, changes),
+        });
+    }
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desired::tests::{DESIRED, apply, live};
+
+    #[test]
+    fn no_drift_after_apply() {
+        let desired: DesiredState = serde_yaml::from_str(DESIRED).unwrap();
+        let mut live = live();
+        assert!(!from_desired(&desired, &live).unwrap().is_empty());
+
+        let actions = desired::plan(&desired, &live).unwrap();
+        apply(&mut live, actions);
+
+        assert!(desired::plan(&desired, &live).unwrap().is_empty());
+        let drift = from_desired(&desired, &live).unwrap();
+        let drift = drift.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert!(drift.is_empty(), "{:?}", drift);
+    }
+
+    #[test]
+    fn reports_a_live_change_after_apply() {
+        let desired: DesiredState = serde_yaml::from_str(DESIRED).unwrap();
+        let mut live = live();
+        let actions = desired::plan(&desired, &live).unwrap();
+        apply(&mut live, actions);
+        live[0].members[0].authorized = false;
+
+        let drift = from_desired(&desired, &live).unwrap();
+        let drift = drift.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            drift,
+            vec!["8056c2e21c000001/1111111111 authorized: true → false"]
+        );
+    }
+}
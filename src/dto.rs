@@ -1,7 +1,49 @@
-use serde::{Deserialize, Serialize};
+use crate::compiler::{self, Program};
+use chrono::{DateTime, Local, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize, de};
+use serde_json::Value;
 use std::fmt::Display;
+use std::net::IpAddr;
+use std::str::FromStr;
 
-#[derive(Deserialize, Debug)]
+/// Local time with the relative age, e.g. `2024-05-01 12:00:00 (3 days ago)`.
+pub fn format_timestamp(millis: i64) -> String {
+    if millis <= 0 {
+        return "Never".to_string();
+    }
+    match DateTime::from_timestamp_millis(millis) {
+        Some(time) => format!(
+            "{} ({})",
+            time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            format_age(millis)
+        ),
+        None => millis.to_string(),
+    }
+}
+
+/// Age relative to now, e.g. `3 days ago` or `in 2 hours`.
+fn format_age(millis: i64) -> String {
+    let delta = Utc::now().timestamp_millis() - millis;
+    let seconds = delta.abs() / 1000;
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        86400..2592000 => (seconds / 86400, "day"),
+        2592000..31536000 => (seconds / 2592000, "month"),
+        _ => (seconds / 31536000, "year"),
+    };
+    let unit = format!("{}{}", unit, if count == 1 { "" } else { "s" });
+
+    if delta < 0 {
+        format!("in {} {}", count, unit)
+    } else {
+        format!("{} {} ago", count, unit)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct StatusResponse {
     pub address: String,
     pub online: bool,
@@ -15,21 +57,119 @@ pub struct StatusResponse {
     pub version_rev: u32,
 }
 
-#[derive(Deserialize, Debug)]
+/// Renders `Label: value` lines of a details view.
+fn details(lines: &[(&str, String)]) -> String {
+    lines
+        .iter()
+        .map(|(label, value)| format!("{}: {}", label, value))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl Display for StatusResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Node ID: {}\nOnline: {}\nVersion: {}.{}.{} (build {})",
+            self.address,
+            self.online,
+            self.version_major,
+            self.version_minor,
+            self.version_rev,
+            self.version_build
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NetworkResponse {
     pub id: String,
     pub name: Option<String>,
     #[serde(rename = "v4AssignMode")]
     pub v4_assign_mode: NetworkV4AssignMode,
+    #[serde(rename = "v6AssignMode")]
+    pub v6_assign_mode: NetworkV6AssignMode,
     #[serde(rename = "creationTime")]
     pub creation_time: i64,
     pub private: bool,
     #[serde(rename = "enableBroadcast")]
     pub enable_broadcast: bool,
     pub mtu: u32,
+    #[serde(rename = "multicastLimit", default)]
+    pub multicast_limit: u32,
+    #[serde(rename = "remoteTraceTarget", default)]
+    pub remote_trace_target: Option<String>,
+    #[serde(rename = "remoteTraceLevel", default)]
+    pub remote_trace_level: u32,
     pub routes: Vec<NetworkRoute>,
     #[serde(rename = "ipAssignmentPools")]
     pub ip_assignment_pools: Vec<NetworkIPAssignmentPool>,
+    #[serde(default, deserialize_with = "dns_or_default")]
+    pub dns: NetworkDNS,
+    #[serde(default)]
+    pub rules: Vec<Value>,
+    #[serde(default)]
+    pub capabilities: Vec<NetworkCapability>,
+    #[serde(default)]
+    pub tags: Vec<NetworkTag>,
+    /// Source the rules were compiled from, kept for the tag and capability names
+    #[serde(rename = "rulesSource", default)]
+    pub rules_source: String,
+}
+
+impl NetworkResponse {
+    pub fn details(&self) -> String {
+        details(&[
+            ("ID", self.id.clone()),
+            ("Name", self.name.clone().unwrap_or("Not set".to_string())),
+            ("Creation Date", format_timestamp(self.creation_time)),
+            ("Is Private", self.private.to_string()),
+            ("Broadcast", self.enable_broadcast.to_string()),
+            ("MTU", self.mtu.to_string()),
+            ("Multicast Limit", self.multicast_limit.to_string()),
+            (
+                "Remote Trace",
+                remote_trace(&self.remote_trace_target, self.remote_trace_level),
+            ),
+            (
+                "Routes",
+                self.routes
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            (
+                "IP Assignment Pools",
+                self.ip_assignment_pools
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            ("Is ZT V4 Assign Mode", self.v4_assign_mode.zt.to_string()),
+            ("V6 Assign Mode", self.v6_assign_mode.to_string()),
+            ("DNS", self.dns.to_string()),
+            ("Rules", compiler::summary(&Program::from_network(self))),
+        ])
+    }
+}
+
+/// Bounds the controller accepts for the network MTU.
+pub const MIN_MTU: u32 = 1280;
+pub const MAX_MTU: u32 = 10000;
+
+/// `Disabled` or `target (level N)`.
+pub fn remote_trace(target: &Option<String>, level: u32) -> String {
+    match target {
+        Some(target) if !target.is_empty() => format!("{} (level {})", target, level),
+        _ => "Disabled".to_string(),
+    }
+}
+
+/// Whether `address` is a 10 digit hex ZeroTier node address.
+pub fn is_valid_address(address: &str) -> bool {
+    address.len() == 10 && address.chars().all(|c| c.is_ascii_hexdigit())
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -37,17 +177,59 @@ pub struct NetworkV4AssignMode {
     pub zt: bool,
 }
 
-impl Display for NetworkResponse {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkV6AssignMode {
+    pub zt: bool,
+    #[serde(rename = "6plane")]
+    pub six_plane: bool,
+    pub rfc4193: bool,
+}
+
+impl Display for NetworkV6AssignMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = if self.name.is_none() || self.name.as_ref().unwrap().is_empty() {
-            self.id.clone()
+        let modes = [
+            (self.zt, "zt"),
+            (self.six_plane, "6plane"),
+            (self.rfc4193, "rfc4193"),
+        ]
+        .iter()
+        .filter(|x| x.0)
+        .map(|x| x.1)
+        .collect::<Vec<&str>>();
+
+        if modes.is_empty() {
+            write!(f, "none")
         } else {
-            format!("{} ({})", self.name.as_ref().unwrap(), self.id)
-        };
-        write!(f, "{}", str)
+            write!(f, "{}", modes.join(", "))
+        }
+    }
+}
+
+impl Display for NetworkResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) if !name.is_empty() => write!(f, "{} ({})", name, self.id),
+            _ => write!(f, "{}", self.id),
+        }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkCapability {
+    pub id: u32,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub rules: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkTag {
+    pub id: u32,
+    #[serde(default)]
+    pub default: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NetworkRoute {
     pub target: String,
@@ -65,6 +247,42 @@ impl Display for NetworkRoute {
     }
 }
 
+impl NetworkRoute {
+    /// Whether `ip` lies inside the route target.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.target
+            .parse::<IpNet>()
+            .is_ok_and(|target| target.contains(ip))
+    }
+}
+
+impl FromStr for NetworkRoute {
+    type Err = String;
+
+    /// Parses `TARGET` or `TARGET@VIA`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, via) = match s.split_once('@') {
+            Some((target, via)) => (target, Some(via)),
+            None => (s, None),
+        };
+        let target = target
+            .parse::<IpNet>()
+            .map_err(|_| format!("invalid route `{}`, expected TARGET[@VIA]", s))?;
+        let via = match via.map(|x| x.parse::<IpAddr>()) {
+            Some(Ok(via)) if via.is_ipv4() == target.addr().is_ipv4() => Some(via.to_string()),
+            Some(_) => return Err(format!("invalid gateway in route `{}`", s)),
+            None if target.prefix_len() == 0 => {
+                return Err(format!("default route `{}` needs a gateway", s));
+            }
+            None => None,
+        };
+        Ok(Self {
+            target: target.trunc().to_string(),
+            via,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NetworkIPAssignmentPool {
     #[serde(rename = "ipRangeStart")]
@@ -79,13 +297,105 @@ impl Display for NetworkIPAssignmentPool {
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl NetworkIPAssignmentPool {
+    /// Parsed bounds, both of the same address family with start <= end.
+    pub fn range(&self) -> Result<(IpAddr, IpAddr), String> {
+        let start = self
+            .ip_range_start
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid start address `{}`", self.ip_range_start))?;
+        let end = self
+            .ip_range_end
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid end address `{}`", self.ip_range_end))?;
+
+        if start.is_ipv4() != end.is_ipv4() {
+            return Err(format!(
+                "{} and {} are of different address families",
+                start, end
+            ));
+        }
+        if start > end {
+            return Err(format!("start {} is greater than end {}", start, end));
+        }
+        Ok((start, end))
+    }
+}
+
+impl FromStr for NetworkIPAssignmentPool {
+    type Err = String;
+
+    /// Parses `START-END`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pool = match s.split_once('-') {
+            Some((start, end)) => Self {
+                ip_range_start: start.to_string(),
+                ip_range_end: end.to_string(),
+            },
+            None => return Err(format!("invalid pool `{}`, expected START-END", s)),
+        };
+        pool.range()
+            .map_err(|e| format!("invalid pool `{}`: {}", s, e))?;
+        Ok(pool)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NetworkDNS {
     pub domain: String,
     pub servers: Vec<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// The controller keeps `"dns": []` until DNS is configured for the first time.
+fn dns_or_default<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NetworkDNS, D::Error> {
+    match Value::deserialize(deserializer)? {
+        value @ Value::Object(_) => serde_json::from_value(value).map_err(de::Error::custom),
+        _ => Ok(NetworkDNS::default()),
+    }
+}
+
+impl Display for NetworkDNS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.domain.is_empty() && self.servers.is_empty() {
+            return write!(f, "Not set");
+        }
+        write!(
+            f,
+            "{} ({})",
+            if self.domain.is_empty() {
+                "no search domain"
+            } else {
+                &self.domain
+            },
+            self.servers.join(", ")
+        )
+    }
+}
+
+/// Whether `domain` looks like a DNS name, e.g. `corp.example.com`.
+pub fn is_valid_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain.len() <= 253
+        && domain.trim_end_matches('.').split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Tag of a member, serialized as `[id, value]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MemberTag(pub u32, pub u32);
+
+impl Display for MemberTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.0, self.1)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MemberResponse {
     pub id: String,
     pub nwid: String,
@@ -101,37 +411,222 @@ pub struct MemberResponse {
     pub last_deauthorized_time: u64,
     #[serde(rename = "ipAssignments")]
     pub ip_assignments: Vec<String>,
-    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<MemberTag>,
+    #[serde(default)]
+    pub capabilities: Vec<u32>,
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(rename = "activeBridge", default)]
+    pub active_bridge: bool,
+    #[serde(rename = "noAutoAssignIps", default)]
+    pub no_auto_assign_ips: bool,
+    #[serde(rename = "ssoExempt", default)]
+    pub sso_exempt: bool,
+    /// Version reported by the member, `-1` until it has connected
+    #[serde(rename = "vMajor", default = "unknown_version")]
+    pub v_major: i32,
+    #[serde(rename = "vMinor", default = "unknown_version")]
+    pub v_minor: i32,
+    #[serde(rename = "vRev", default = "unknown_version")]
+    pub v_rev: i32,
+    #[serde(rename = "vProto", default = "unknown_version")]
+    pub v_proto: i32,
+}
+
+fn unknown_version() -> i32 {
+    -1
+}
+
+impl MemberResponse {
+    pub fn details(&self) -> String {
+        details(&[
+            ("ID", self.id.clone()),
+            ("Network", self.nwid.clone()),
+            ("Name", self.name.clone().unwrap_or("Not set".to_string())),
+            ("Authorized", self.authorized.to_string()),
+            ("IP Assignments", self.ip_assignments.join(",")),
+            (
+                "Tags",
+                self.tags
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            (
+                "Capabilities",
+                self.capabilities
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            ("Creation Date", format_timestamp(self.creation_time as i64)),
+            (
+                "Last Authorized",
+                format_timestamp(self.last_authorized_time as i64),
+            ),
+            (
+                "Last Deauthorized",
+                format_timestamp(self.last_deauthorized_time as i64),
+            ),
+            (
+                "Authentication Expiry",
+                format_timestamp(self.authentication_expiry_time as i64),
+            ),
+            ("Revision", self.revision.to_string()),
+            ("Active Bridge", self.active_bridge.to_string()),
+            ("No Auto Assign IPs", self.no_auto_assign_ips.to_string()),
+            ("SSO Exempt", self.sso_exempt.to_string()),
+            ("Version", self.version()),
+        ])
+    }
+
+    /// Tags with `id` set to `value`, or without it when `value` is `None`.
+    pub fn tags_with(&self, id: u32, value: Option<u32>) -> Vec<MemberTag> {
+        let mut tags = self.tags.clone();
+        tags.retain(|x| x.0 != id);
+        tags.extend(value.map(|x| MemberTag(id, x)));
+        tags
+    }
+
+    /// Remote version, e.g. `1.14.2 (protocol 13)`.
+    pub fn version(&self) -> String {
+        if self.v_major < 0 {
+            return "Unknown".to_string();
+        }
+        format!(
+            "{}.{}.{} (protocol {})",
+            self.v_major, self.v_minor, self.v_rev, self.v_proto
+        )
+    }
 }
 
 impl Display for MemberResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ips = self.ip_assignments.join(", ");
-        write!(
-            f,
-            "{}",
-            if self.name.is_some() {
-                format!(
-                    "{} ({} ** {})",
-                    self.name.as_ref().unwrap(),
-                    self.id,
-                    if self.ip_assignments.is_empty() {
-                        "No IPs"
-                    } else {
-                        &*ips
-                    }
-                )
+        let ips = if self.ip_assignments.is_empty() {
+            "No IPs".to_string()
+        } else {
+            self.ip_assignments.join(", ")
+        };
+        match &self.name {
+            Some(name) => write!(f, "{} ({} ** {})", name, self.id, ips),
+            None => write!(f, "{} ({})", self.id, ips),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeerPath {
+    pub active: bool,
+    /// Physical endpoint, e.g. `192.0.2.1/9993`
+    pub address: String,
+    #[serde(default)]
+    pub expired: bool,
+    #[serde(default)]
+    pub preferred: bool,
+    #[serde(rename = "lastReceive", default)]
+    pub last_receive: u64,
+}
+
+/// A node the local service talks to, from the `/peer` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeerResponse {
+    pub address: String,
+    /// Round trip time in milliseconds, `-1` when unknown
+    pub latency: i32,
+    pub role: String,
+    pub version: String,
+    #[serde(default)]
+    pub paths: Vec<PeerPath>,
+}
+
+impl PeerResponse {
+    /// Direct path in use, the preferred one when there are several.
+    pub fn path(&self) -> Option<&PeerPath> {
+        let mut paths = self.paths.iter().filter(|x| x.active && !x.expired);
+        paths.clone().find(|x| x.preferred).or_else(|| paths.next())
+    }
+
+    /// Peers without a direct path are reached through a root.
+    pub fn is_online(&self) -> bool {
+        self.path().is_some() || self.latency >= 0
+    }
+}
+
+/// Short presence of a member, e.g. `online, 12 ms, direct`; no peer means offline.
+pub fn presence(peer: Option<&PeerResponse>) -> String {
+    match peer {
+        Some(peer) if peer.is_online() => {
+            let mut parts = vec!["online".to_string()];
+            if peer.latency >= 0 {
+                parts.push(format!("{} ms", peer.latency));
+            }
+            parts.push(match peer.path() {
+                Some(_) => "direct".to_string(),
+                None => "relayed".to_string(),
+            });
+            parts.join(", ")
+        }
+        _ => "offline".to_string(),
+    }
+}
+
+pub fn presence_details(peer: Option<&PeerResponse>) -> String {
+    let peer = match peer {
+        Some(peer) if peer.is_online() => peer,
+        _ => return details(&[("Status", "Offline".to_string())]),
+    };
+    let last_seen = peer.paths.iter().map(|x| x.last_receive).max();
+
+    details(&[
+        ("Status", "Online".to_string()),
+        (
+            "Latency",
+            match peer.latency {
+                0.. => format!("{} ms", peer.latency),
+                _ => "Unknown".to_string(),
+            },
+        ),
+        (
+            "Path",
+            match peer.path() {
+                Some(path) => format!("Direct via {}", path.address),
+                None => "Relayed".to_string(),
+            },
+        ),
+        ("Last Seen", format_timestamp(last_seen.unwrap_or(0) as i64)),
+        ("Role", peer.role.clone()),
+        (
+            "Client Version",
+            if peer.version.starts_with('-') {
+                "Unknown".to_string()
             } else {
-                format!(
-                    "{} ({})",
-                    self.id,
-                    if self.ip_assignments.is_empty() {
-                        "No IPs"
-                    } else {
-                        &*ips
-                    }
-                )
+                peer.version.clone()
             },
-        )
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::tests::empty_network;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_dns_before_it_is_configured() {
+        let mut network = serde_json::to_value(empty_network()).unwrap();
+        for dns in [json!([]), Value::Null] {
+            network["dns"] = dns;
+            let decoded: NetworkResponse = serde_json::from_value(network.clone()).unwrap();
+            assert!(decoded.dns.domain.is_empty() && decoded.dns.servers.is_empty());
+        }
+
+        network["dns"] = json!({ "domain": "office.lan", "servers": ["10.0.0.1"] });
+        let decoded: NetworkResponse = serde_json::from_value(network).unwrap();
+        assert_eq!(decoded.dns.domain, "office.lan");
+        assert_eq!(decoded.dns.servers, vec!["10.0.0.1"]);
     }
 }
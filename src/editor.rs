@@ -0,0 +1,524 @@
+use crate::api::EditNetwork;
+use crate::compiler::{self, Program, TagDefinition};
+use crate::dto::{
+    MAX_MTU, MIN_MTU, NetworkDNS, NetworkIPAssignmentPool, NetworkRoute, is_valid_address,
+    is_valid_domain, remote_trace,
+};
+use crate::filter::{MemberFilter, MemberSort};
+use crate::rules;
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Editor, Input, Select};
+use ipnet::IpNet;
+use regex::Regex;
+use serde_json::Value;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+/// Interactive editor for a list, `prompt` asks for a new entry or changes an existing one.
+///
+/// Returns the edited list when saved, `None` when discarded.
+pub fn edit_list<T: Display + Clone>(
+    title: &str,
+    mut items: Vec<T>,
+    prompt: impl Fn(Option<&T>) -> Option<T>,
+) -> Option<Vec<T>> {
+    let mut modified = false;
+
+    loop {
+        let mut entries = items.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        entries.push("Add...".to_string());
+        entries.push("Save".to_string());
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "{}{}",
+                title,
+                if modified { " (modified)" } else { "" }
+            ))
+            .items(&entries)
+            .default(0)
+            .interact_opt()
+            .unwrap();
+
+        match selection {
+            Some(index) if index == items.len() => {
+                if let Some(item) = prompt(None) {
+                    items.push(item);
+                    modified = true;
+                }
+            }
+            Some(index) if index == items.len() + 1 => return Some(items),
+            Some(index) => {
+                let action = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt(items[index].to_string())
+                    .items(&["Edit", "Move up", "Move down", "Remove"])
+                    .default(0)
+                    .interact_opt()
+                    .unwrap();
+
+                match action {
+                    Some(0) => {
+                        if let Some(item) = prompt(Some(&items[index])) {
+                            items[index] = item;
+                            modified = true;
+                        }
+                    }
+                    Some(1) if index > 0 => {
+                        items.swap(index, index - 1);
+                        modified = true;
+                    }
+                    Some(2) if index + 1 < items.len() => {
+                        items.swap(index, index + 1);
+                        modified = true;
+                    }
+                    Some(3) => {
+                        items.remove(index);
+                        modified = true;
+                    }
+                    _ => {}
+                }
+            }
+            None => {
+                if !modified
+                    || Confirm::new()
+                        .with_prompt("Discard changes?")
+                        .interact()
+                        .unwrap()
+                {
+                    println!("{}", "Changes discarded".bright_blue());
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Asks for a managed route, a `/0` target is a default route and needs a gateway.
+pub fn prompt_route(current: Option<&NetworkRoute>) -> Option<NetworkRoute> {
+    let target: String = Input::new()
+        .with_prompt("Target")
+        .default(
+            current
+                .map(|x| x.target.clone())
+                .unwrap_or("192.168.192.0/24".to_string()),
+        )
+        .validate_with(|x: &String| {
+            x.parse::<IpNet>()
+                .map(|_| ())
+                .map_err(|_| "Expected a network like 10.0.0.0/24 or 0.0.0.0/0")
+        })
+        .interact_text()
+        .ok()?;
+    let target_net = target.parse::<IpNet>().unwrap();
+
+    let via: String = Input::new()
+        .with_prompt("Via (empty is null)")
+        .default(current.and_then(|x| x.via.clone()).unwrap_or_default())
+        .allow_empty(true)
+        .validate_with(|x: &String| {
+            if x.is_empty() {
+                return if target_net.prefix_len() == 0 {
+                    Err("A default route needs a gateway")
+                } else {
+                    Ok(())
+                };
+            }
+            match x.parse::<IpAddr>() {
+                Ok(ip) if ip.is_ipv4() == target_net.addr().is_ipv4() => Ok(()),
+                Ok(_) => Err("Gateway must be of the same address family as the target"),
+                Err(_) => Err("Expected an IP address"),
+            }
+        })
+        .interact_text()
+        .ok()?;
+
+    Some(NetworkRoute {
+        target: target_net.trunc().to_string(),
+        via: if via.is_empty() { None } else { Some(via) },
+    })
+}
+
+/// Asks for an IP assignment pool, IPv4 or IPv6.
+pub fn prompt_pool(current: Option<&NetworkIPAssignmentPool>) -> Option<NetworkIPAssignmentPool> {
+    let start: String = Input::new()
+        .with_prompt("IP Range Start")
+        .default(
+            current
+                .map(|x| x.ip_range_start.clone())
+                .unwrap_or("192.168.192.1".to_string()),
+        )
+        .validate_with(|x: &String| {
+            x.parse::<IpAddr>()
+                .map(|_| ())
+                .map_err(|_| "Expected an IP address")
+        })
+        .interact_text()
+        .ok()?;
+
+    let end: String = Input::new()
+        .with_prompt("IP Range End")
+        .default(
+            current
+                .map(|x| x.ip_range_end.clone())
+                .unwrap_or("192.168.192.254".to_string()),
+        )
+        .validate_with(|x: &String| {
+            NetworkIPAssignmentPool {
+                ip_range_start: start.clone(),
+                ip_range_end: x.clone(),
+            }
+            .range()
+            .map(|_| ())
+        })
+        .interact_text()
+        .ok()?;
+
+    Some(NetworkIPAssignmentPool {
+        ip_range_start: start,
+        ip_range_end: end,
+    })
+}
+
+/// Asks for a member IP address, warning when no managed route covers it.
+///
+/// A new address starts from `suggestion`, e.g. the next free address of the pools.
+pub fn prompt_ip(
+    current: Option<&String>,
+    routes: &[NetworkRoute],
+    suggestion: Option<IpAddr>,
+) -> Option<String> {
+    let initial = match current {
+        Some(current) => current.clone(),
+        None => suggestion.map(|x| x.to_string()).unwrap_or_default(),
+    };
+    let ip: String = Input::new()
+        .with_prompt("IP address")
+        .with_initial_text(initial)
+        .validate_with(|x: &String| {
+            x.parse::<IpAddr>()
+                .map(|_| ())
+                .map_err(|_| "Expected an IP address")
+        })
+        .interact_text()
+        .ok()?;
+    let ip = ip.parse::<IpAddr>().unwrap();
+
+    if !routes.iter().any(|x| x.contains(&ip)) {
+        println!(
+            "{}",
+            format!("⚠ {} is outside every managed route of the network", ip).yellow()
+        );
+    }
+    Some(ip.to_string())
+}
+
+/// Asks for a value of `tag`, one of its enums or a custom value.
+///
+/// Returns `Some(None)` when the tag should be removed, `None` when cancelled.
+pub fn prompt_tag_value(tag: &TagDefinition) -> Option<Option<u32>> {
+    let mut enums = tag.enums.iter().collect::<Vec<(&String, &u32)>>();
+    enums.sort_by_key(|x| x.1);
+    let mut values = enums
+        .iter()
+        .map(|(name, value)| format!("{} ({})", name, value))
+        .collect::<Vec<String>>();
+    values.push("Custom value...".to_string());
+    values.push("Remove".to_string());
+
+    match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Value of {}", tag.name))
+        .items(&values)
+        .default(0)
+        .interact_opt()
+        .unwrap()?
+    {
+        i if i < enums.len() => Some(Some(*enums[i].1)),
+        i if i == enums.len() => {
+            let value: String = Input::new()
+                .with_prompt("Value (number, enum or flag names)")
+                .validate_with(|x: &String| tag.value(x).map(|_| ()).ok_or("Unknown value"))
+                .interact_text()
+                .ok()?;
+            Some(tag.value(&value))
+        }
+        _ => Some(None),
+    }
+}
+
+/// Asks for a DNS server address.
+pub fn prompt_dns_server(current: Option<&String>) -> Option<String> {
+    Input::new()
+        .with_prompt("DNS server")
+        .with_initial_text(current.cloned().unwrap_or_default())
+        .validate_with(|x: &String| {
+            x.parse::<IpAddr>()
+                .map(|_| ())
+                .map_err(|_| "Expected an IP address")
+        })
+        .interact_text()
+        .ok()
+}
+
+/// Asks for the search domain and the server list, `None` when discarded.
+pub fn edit_dns(current: &NetworkDNS) -> Option<NetworkDNS> {
+    let domain: String = Input::new()
+        .with_prompt("Search domain (empty is none)")
+        .with_initial_text(current.domain.clone())
+        .allow_empty(true)
+        .validate_with(|x: &String| {
+            if x.is_empty() || is_valid_domain(x) {
+                Ok(())
+            } else {
+                Err("Expected a domain like corp.example.com")
+            }
+        })
+        .interact_text()
+        .ok()?;
+
+    let servers = edit_list("DNS servers", current.servers.clone(), prompt_dns_server)?;
+
+    Some(NetworkDNS { domain, servers })
+}
+
+/// Edits private, broadcast, MTU, multicast limit and remote trace settings.
+///
+/// Returns the changed settings when saved, `None` when discarded.
+pub fn edit_settings(mut data: EditNetwork) -> Option<EditNetwork> {
+    let mut modified = false;
+
+    loop {
+        let items = [
+            format!("Private: {}", data.private),
+            format!("Broadcast: {}", data.enable_broadcast),
+            format!("MTU: {}", data.mtu),
+            format!("Multicast limit: {}", data.multicast_limit),
+            format!(
+                "Remote trace: {}",
+                remote_trace(&data.remote_trace_target, data.remote_trace_level)
+            ),
+            "Save".to_string(),
+        ];
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Settings{}",
+                if modified { " (modified)" } else { "" }
+            ))
+            .items(&items)
+            .default(0)
+            .interact_opt()
+            .unwrap();
+
+        match selection {
+            Some(0) => data.private = !data.private,
+            Some(1) => data.enable_broadcast = !data.enable_broadcast,
+            Some(2) => {
+                match Input::<u32>::new()
+                    .with_prompt(format!("MTU ({}-{})", MIN_MTU, MAX_MTU))
+                    .default(data.mtu)
+                    .validate_with(|x: &u32| {
+                        if (MIN_MTU..=MAX_MTU).contains(x) {
+                            Ok(())
+                        } else {
+                            Err(format!("MTU must be from {} to {}", MIN_MTU, MAX_MTU))
+                        }
+                    })
+                    .interact_text()
+                {
+                    Ok(mtu) => data.mtu = mtu,
+                    Err(_) => continue,
+                }
+            }
+            Some(3) => {
+                match Input::<u32>::new()
+                    .with_prompt("Maximum recipients of a multicast packet (0 disables)")
+                    .default(data.multicast_limit)
+                    .interact_text()
+                {
+                    Ok(limit) => data.multicast_limit = limit,
+                    Err(_) => continue,
+                }
+            }
+            Some(4) => {
+                let target: String = match Input::new()
+                    .with_prompt("Trace target node (empty disables)")
+                    .with_initial_text(data.remote_trace_target.clone().unwrap_or_default())
+                    .allow_empty(true)
+                    .validate_with(|x: &String| {
+                        if x.is_empty() || is_valid_address(x) {
+                            Ok(())
+                        } else {
+                            Err("Expected a 10 digit node address")
+                        }
+                    })
+                    .interact_text()
+                {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+
+                if target.is_empty() {
+                    data.remote_trace_target = None;
+                    data.remote_trace_level = 0;
+                } else {
+                    match Input::<u32>::new()
+                        .with_prompt("Trace level")
+                        .default(data.remote_trace_level)
+                        .interact_text()
+                    {
+                        Ok(level) => data.remote_trace_level = level,
+                        Err(_) => continue,
+                    }
+                    data.remote_trace_target = Some(target.to_lowercase());
+                }
+            }
+            Some(_) => return Some(data),
+            None => {
+                if !modified
+                    || Confirm::new()
+                        .with_prompt("Discard changes?")
+                        .interact()
+                        .unwrap()
+                {
+                    println!("{}", "Changes discarded".bright_blue());
+                    return None;
+                }
+                continue;
+            }
+        }
+        modified = true;
+    }
+}
+
+/// Changes one quick filter or the sort order, `None` when cancelled.
+pub fn edit_filter(current: &MemberFilter) -> Option<MemberFilter> {
+    let mut filter = current.clone();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Filter and sort")
+        .items(&[
+            "Show all (clear filters)",
+            "Only authorized",
+            "Only unauthorized",
+            if filter.no_ip {
+                "Show members with IPs too"
+            } else {
+                "Only members without IP"
+            },
+            "Name matches regex...",
+            "Sort by...",
+        ])
+        .default(0)
+        .interact_opt()
+        .unwrap()?;
+
+    match selection {
+        0 => return Some(MemberFilter::default()),
+        1 => (filter.authorized, filter.unauthorized) = (true, false),
+        2 => (filter.authorized, filter.unauthorized) = (false, true),
+        3 => filter.no_ip = !filter.no_ip,
+        4 => {
+            let pattern: String = Input::new()
+                .with_prompt("Name regex (empty is any)")
+                .with_initial_text(
+                    filter
+                        .name
+                        .as_ref()
+                        .map(|x| x.to_string())
+                        .unwrap_or_default(),
+                )
+                .allow_empty(true)
+                .validate_with(|x: &String| Regex::new(x).map(|_| ()).map_err(|e| e.to_string()))
+                .interact_text()
+                .ok()?;
+            filter.name = if pattern.is_empty() {
+                None
+            } else {
+                Regex::new(&pattern).ok()
+            };
+        }
+        _ => {
+            let sorts = MemberSort::value_variants();
+            let mut items = vec!["Controller order".to_string()];
+            items.extend(sorts.iter().map(|x| x.to_string()));
+            filter.sort = match Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Sort by")
+                .items(&items)
+                .default(0)
+                .interact_opt()
+                .unwrap()?
+            {
+                0 => None,
+                i => Some(sorts[i - 1]),
+            };
+        }
+    }
+
+    Some(filter)
+}
+
+/// Opens the rules as JSON in `$EDITOR` until they are valid, `None` when discarded.
+pub fn edit_rules(current: &[Value]) -> Option<Vec<Value>> {
+    let mut text = serde_json::to_string_pretty(current).unwrap();
+
+    loop {
+        text = match Editor::new().extension(".json").edit(&text) {
+            Ok(Some(text)) => text,
+            Ok(None) => return None,
+            Err(e) => {
+                println!("❌ Cannot start editor: {}", e);
+                return None;
+            }
+        };
+
+        match rules::parse(&text) {
+            Ok(rules) => return Some(rules),
+            Err(e) => {
+                println!("❌ {}", e);
+                if !Confirm::new()
+                    .with_prompt("Edit again?")
+                    .default(true)
+                    .interact()
+                    .unwrap()
+                {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Opens rules source in `$EDITOR` until it compiles, `None` when discarded.
+///
+/// Returns the edited source with the compiled program.
+pub fn edit_source(current: &str) -> Option<(String, Program)> {
+    let mut text = current.to_string();
+
+    loop {
+        text = match Editor::new().extension(".ztrules").edit(&text) {
+            Ok(Some(text)) => text,
+            Ok(None) => return None,
+            Err(e) => {
+                println!("❌ Cannot start editor: {}", e);
+                return None;
+            }
+        };
+
+        match compiler::compile(&text) {
+            Ok(program) => return Some((text, program)),
+            Err(e) => {
+                println!("❌ {}", e);
+                if !Confirm::new()
+                    .with_prompt("Edit again?")
+                    .default(true)
+                    .interact()
+                    .unwrap()
+                {
+                    return None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use reqwest::StatusCode;
+use std::fmt::Display;
+
+/// Errors returned by [`crate::api::APIClient`].
+#[derive(Debug)]
+pub enum Error {
+    /// The controller rejected the token (401/403).
+    Unauthorized,
+    /// The requested network or member does not exist (404).
+    NotFound(String),
+    /// Any other non-success status, with the response body.
+    Controller { status: StatusCode, body: String },
+    /// The response body does not match the expected structure.
+    Decode(serde_json::Error),
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unauthorized => write!(f, "unauthorized, check the API token"),
+            Error::NotFound(path) => write!(f, "not found: {}", path),
+            Error::Controller { status, body } if body.trim().is_empty() => {
+                write!(f, "controller returned {}", status)
+            }
+            Error::Controller { status, body } => {
+                write!(f, "controller returned {}: {}", status, body.trim())
+            }
+            Error::Decode(e) => write!(f, "cannot decode controller response: {}", e),
+            Error::Transport(e) => {
+                write!(f, "{}", e)?;
+                // reqwest keeps the cause, e.g. a refused connection, in the source chain
+                let mut source = std::error::Error::source(e);
+                while let Some(cause) = source {
+                    write!(f, ": {}", cause)?;
+                    source = cause.source();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode(e) => Some(e),
+            Error::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Decode(e)
+    }
+}
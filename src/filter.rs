@@ -0,0 +1,147 @@
+use crate::dto::MemberResponse;
+use clap::{Args, ValueEnum};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use regex::Regex;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MemberSort {
+    Name,
+    Id,
+    /// Newest first
+    Created,
+    /// Most recently authorized first
+    LastAuthorized,
+}
+
+impl Display for MemberSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sort = match self {
+            Self::Name => "name",
+            Self::Id => "ID",
+            Self::Created => "creation time",
+            Self::LastAuthorized => "last authorized",
+        };
+        write!(f, "{}", sort)
+    }
+}
+
+/// Search, quick filters and sort order of a member listing.
+#[derive(Args, Clone, Default)]
+pub struct MemberFilter {
+    /// Fuzzy search over name, node ID and IPs, best matches first
+    #[arg(long, short)]
+    pub search: Option<String>,
+    /// Only authorized members
+    #[arg(long, conflicts_with = "unauthorized")]
+    pub authorized: bool,
+    /// Only unauthorized members
+    #[arg(long)]
+    pub unauthorized: bool,
+    /// Only members without IP assignments
+    #[arg(long)]
+    pub no_ip: bool,
+    /// Only members whose name matches the regular expression
+    #[arg(long, value_name = "REGEX")]
+    pub name: Option<Regex>,
+    #[arg(long)]
+    pub sort: Option<MemberSort>,
+}
+
+/// Fuzzy match score of `text` against `pattern`, `None` when it does not match.
+pub fn fuzzy_score(text: &str, pattern: &str) -> Option<i64> {
+    SkimMatcherV2::default().fuzzy_match(text, pattern)
+}
+
+impl MemberFilter {
+    pub fn is_empty(&self) -> bool {
+        self.search.is_none()
+            && !self.authorized
+            && !self.unauthorized
+            && !self.no_ip
+            && self.name.is_none()
+            && self.sort.is_none()
+    }
+
+    fn matches(&self, member: &MemberResponse) -> bool {
+        (!self.authorized || member.authorized)
+            && (!self.unauthorized || !member.authorized)
+            && (!self.no_ip || member.ip_assignments.is_empty())
+            && self.name.as_ref().is_none_or(|regex| {
+                member
+                    .name
+                    .as_ref()
+                    .is_some_and(|name| regex.is_match(name))
+            })
+    }
+
+    /// Positions of the matching members in display order.
+    pub fn apply(&self, members: &[MemberResponse]) -> Vec<usize> {
+        let mut scored = members
+            .iter()
+            .enumerate()
+            .filter(|(_, member)| self.matches(member))
+            .filter_map(|(i, member)| match &self.search {
+                Some(pattern) => {
+                    let text = format!(
+                        "{} {} {}",
+                        member.name.as_deref().unwrap_or_default(),
+                        member.id,
+                        member.ip_assignments.join(" ")
+                    );
+                    fuzzy_score(&text, pattern).map(|score| (i, score))
+                }
+                None => Some((i, 0)),
+            })
+            .collect::<Vec<(usize, i64)>>();
+
+        // Stable sorts keep the controller order between equal keys
+        scored.sort_by_key(|x| std::cmp::Reverse(x.1));
+        let mut indices = scored.into_iter().map(|x| x.0).collect::<Vec<usize>>();
+        match self.sort {
+            Some(MemberSort::Name) => indices.sort_by_key(|i| {
+                let member = &members[*i];
+                (
+                    member.name.is_none(),
+                    member.name.clone().unwrap_or_default().to_lowercase(),
+                )
+            }),
+            Some(MemberSort::Id) => indices.sort_by_key(|i| members[*i].id.clone()),
+            Some(MemberSort::Created) => {
+                indices.sort_by_key(|i| std::cmp::Reverse(members[*i].creation_time))
+            }
+            Some(MemberSort::LastAuthorized) => {
+                indices.sort_by_key(|i| std::cmp::Reverse(members[*i].last_authorized_time))
+            }
+            None => {}
+        }
+        indices
+    }
+}
+
+/// Short description for prompts, e.g. `unauthorized, sorted by name`.
+impl Display for MemberFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(search) = &self.search {
+            parts.push(format!("matching \"{}\"", search));
+        }
+        if self.authorized {
+            parts.push("authorized".to_string());
+        }
+        if self.unauthorized {
+            parts.push("unauthorized".to_string());
+        }
+        if self.no_ip {
+            parts.push("without IP".to_string());
+        }
+        if let Some(name) = &self.name {
+            parts.push(format!("name ~ /{}/", name));
+        }
+        if let Some(sort) = self.sort {
+            parts.push(format!("sorted by {}", sort));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -0,0 +1,992 @@
+use crate::allocator::{next_free, used_ips};
+use crate::api::{APIClient, EditMember, EditNetwork};
+use crate::check::check;
+use crate::compiler::{self, Program};
+use crate::dto::{
+    MemberResponse, NetworkResponse, NetworkV4AssignMode, NetworkV6AssignMode, PeerResponse,
+    StatusResponse, presence, presence_details,
+};
+use crate::editor::{
+    edit_dns, edit_filter, edit_list, edit_rules, edit_settings, edit_source, prompt_ip,
+    prompt_pool, prompt_route, prompt_tag_value,
+};
+use crate::filter::{MemberFilter, fuzzy_score};
+use colored::Colorize;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::process::exit;
+
+struct State {
+    client: APIClient,
+    networks: Vec<NetworkResponse>,
+    members: Option<Vec<MemberResponse>>,
+    /// Peers of the local service by node ID, `None` when `/peer` is unavailable
+    peers: Option<HashMap<String, PeerResponse>>,
+    selected_network: Option<usize>,
+    status: StatusResponse,
+    network_search: Option<String>,
+    member_filter: MemberFilter,
+}
+
+pub async fn run(client: APIClient, default_network: Option<String>) -> io::Result<()> {
+    println!("{}", "⏳ Fetching networks".yellow());
+
+    let status = match client.status().await {
+        Ok(r) => r,
+        Err(e) => {
+            println!("❌ Request failed: {}", e);
+            exit(1);
+        }
+    };
+
+    println!("Node ID: {}", status.address);
+    println!(
+        "{}",
+        "Use arrows to navigate up & down. Use `q` to return back.".bright_magenta()
+    );
+
+    let networks = match client.networks_detailed().await {
+        Ok(r) => r,
+        Err(e) => {
+            println!("❌ Request failed: {}", e);
+            exit(1);
+        }
+    };
+
+    let selected_network = default_network.and_then(|id| networks.iter().position(|x| x.id == id));
+
+    let mut state = State {
+        status,
+        client,
+        networks,
+        selected_network,
+        members: None,
+        peers: None,
+        network_search: None,
+        member_filter: MemberFilter::default(),
+    };
+
+    loop {
+        if state.selected_network.is_some() {
+            if state.members.is_some() {
+                members_list(&mut state).await;
+            } else {
+                network_options(&mut state).await;
+            }
+        } else {
+            networks_list(&mut state).await;
+        }
+    }
+}
+
+async fn networks_list(state: &mut State) {
+    let view = match &state.network_search {
+        Some(pattern) => {
+            let mut scored = state
+                .networks
+                .iter()
+                .enumerate()
+                .filter_map(|(i, x)| {
+                    fuzzy_score(
+                        &format!("{} {}", x.name.as_deref().unwrap_or_default(), x.id),
+                        pattern,
+                    )
+                    .map(|score| (i, score))
+                })
+                .collect::<Vec<(usize, i64)>>();
+            scored.sort_by_key(|x| std::cmp::Reverse(x.1));
+            scored.into_iter().map(|x| x.0).collect::<Vec<usize>>()
+        }
+        None => (0..state.networks.len()).collect(),
+    };
+
+    let mut items = view
+        .iter()
+        .map(|i| state.networks[*i].to_string())
+        .collect::<Vec<String>>();
+    items.push("Search...".to_string());
+    items.push("Create new network...".to_string());
+    items.push("Exit".to_string());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(match &state.network_search {
+            Some(pattern) => format!("Networks matching \"{}\"", pattern),
+            None => "Networks".to_string(),
+        })
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .unwrap();
+
+    if let Some(index) = selection {
+        if index == view.len() {
+            state.network_search = prompt_search(state.network_search.as_deref());
+            return;
+        }
+
+        if index == view.len() + 1 {
+            match state.client.create_network(&state.status.address).await {
+                Ok(r) => {
+                    println!("⚡ Network created: {}", r.id);
+                    state.networks.push(r)
+                }
+                Err(e) => {
+                    println!("❌ Request failed: {}", e)
+                }
+            }
+            return;
+        }
+
+        if index == view.len() + 2 {
+            exit(0);
+        }
+
+        state.selected_network = Some(view[index]);
+    } else if state.network_search.is_some() {
+        state.network_search = None;
+    }
+}
+
+/// Asks for a search pattern, `None` when it is left empty.
+fn prompt_search(current: Option<&str>) -> Option<String> {
+    Input::<String>::new()
+        .with_prompt("Search (empty shows all)")
+        .with_initial_text(current.unwrap_or_default())
+        .allow_empty(true)
+        .interact_text()
+        .ok()
+        .filter(|x| !x.is_empty())
+}
+
+async fn network_options(state: &mut State) {
+    let selected_index = match state.selected_network {
+        Some(index) => index,
+        None => return,
+    };
+    let network_id = state.networks[selected_index].id.clone();
+    let network_name = state.networks[selected_index].to_string();
+    let zt_mode = state.networks[selected_index].v4_assign_mode.zt;
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Network {}", network_name))
+        .items(&[
+            "Info",
+            "Members",
+            "Rename",
+            "Settings",
+            "IP assignment pools",
+            "Routes",
+            if zt_mode {
+                "Disable v4 ZT Mode"
+            } else {
+                "Enable v4 ZT Mode"
+            },
+            "IPv6 assign modes",
+            "DNS",
+            "Flow rules",
+            "Check",
+            "Delete",
+        ])
+        .default(0)
+        .interact_opt()
+        .unwrap();
+
+    match selection {
+        Some(index) => match index {
+            0 => {
+                println!("\n{}", state.networks[selected_index].details())
+            }
+            1 => {
+                println!("{}", "⏳ Fetching members".yellow());
+                let members = match state.client.members_detailed(&network_id).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        println!("❌ Request failed: {}", e);
+                        return;
+                    }
+                };
+                if members.is_empty() {
+                    println!(
+                        "{}",
+                        "🌧  This network doesn't contains any members".bright_blue()
+                    );
+                    return;
+                }
+
+                state.members = Some(members);
+                let peers = state.client.peers().await.ok();
+                state.peers =
+                    peers.map(|x| x.into_iter().map(|x| (x.address.clone(), x)).collect());
+            }
+            2 => {
+                let network = &mut state.networks[selected_index];
+                let name: String = Input::new()
+                    .with_prompt("New name")
+                    .interact_text()
+                    .unwrap_or("".to_string());
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            name: Some(name.clone()),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.name = Some(name);
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            3 => {
+                let network = &mut state.networks[selected_index];
+                let data = match edit_settings(EditNetwork::from(&*network)) {
+                    Some(data) => data,
+                    None => return,
+                };
+
+                if let Err(e) = state.client.edit_network(&network_id, data.clone()).await {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.private = data.private;
+                    network.enable_broadcast = data.enable_broadcast;
+                    network.mtu = data.mtu;
+                    network.multicast_limit = data.multicast_limit;
+                    network.remote_trace_target = data.remote_trace_target;
+                    network.remote_trace_level = data.remote_trace_level;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            4 => {
+                let network = &mut state.networks[selected_index];
+                let ips = match edit_list(
+                    "IP assignment pools",
+                    network.ip_assignment_pools.clone(),
+                    prompt_pool,
+                ) {
+                    Some(ips) => ips,
+                    None => return,
+                };
+
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            ip_assignment_pools: ips.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.ip_assignment_pools = ips;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            5 => {
+                let network = &mut state.networks[selected_index];
+                let routes = match edit_list("Routes", network.routes.clone(), prompt_route) {
+                    Some(routes) => routes,
+                    None => return,
+                };
+
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            routes: routes.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.routes = routes;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            6 => {
+                let network = &mut state.networks[selected_index];
+                let v4_assign_mode = NetworkV4AssignMode {
+                    zt: !network.v4_assign_mode.zt,
+                };
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            v4_assign_mode: v4_assign_mode.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.v4_assign_mode = v4_assign_mode;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            7 => {
+                let network = &mut state.networks[selected_index];
+                let mode = &network.v6_assign_mode;
+                let selected = match MultiSelect::with_theme(&ColorfulTheme::default())
+                    .with_prompt("IPv6 assign modes (space to toggle)")
+                    .items(&[
+                        "ZT (assign from IP pools)",
+                        "6PLANE (/80 routable for each member)",
+                        "RFC4193 (/128 for each member)",
+                    ])
+                    .defaults(&[mode.zt, mode.six_plane, mode.rfc4193])
+                    .interact_opt()
+                    .unwrap()
+                {
+                    Some(selected) => selected,
+                    None => return,
+                };
+
+                let v6_assign_mode = NetworkV6AssignMode {
+                    zt: selected.contains(&0),
+                    six_plane: selected.contains(&1),
+                    rfc4193: selected.contains(&2),
+                };
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            v6_assign_mode: v6_assign_mode.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.v6_assign_mode = v6_assign_mode;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            8 => {
+                let network = &mut state.networks[selected_index];
+                let dns = match edit_dns(&network.dns) {
+                    Some(dns) => dns,
+                    None => return,
+                };
+
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            dns: dns.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.dns = dns;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            9 => {
+                let network = &mut state.networks[selected_index];
+                let action = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Flow rules")
+                    .items(&[
+                        "View source",
+                        "View JSON",
+                        "Edit source in $EDITOR",
+                        "Edit JSON in $EDITOR",
+                    ])
+                    .default(0)
+                    .interact_opt()
+                    .unwrap();
+
+                let edited = match action {
+                    Some(0) => {
+                        println!("{}", compiler::source(network));
+                        return;
+                    }
+                    Some(1) => {
+                        println!("{}", serde_json::to_string_pretty(&network.rules).unwrap());
+                        return;
+                    }
+                    Some(2) => edit_source(&compiler::source(network)),
+                    // The old source no longer matches, names fall back to the ids
+                    Some(3) => edit_rules(&network.rules).map(|rules| {
+                        (
+                            String::new(),
+                            Program {
+                                rules,
+                                ..Program::from_network(network)
+                            },
+                        )
+                    }),
+                    _ => return,
+                };
+                let (source, program) = match edited {
+                    Some(edited) => edited,
+                    None => return,
+                };
+
+                println!("\n{}\n", compiler::summary(&program));
+                if !Confirm::new()
+                    .with_prompt("Apply these rules?")
+                    .interact()
+                    .unwrap()
+                {
+                    return;
+                }
+
+                if let Err(e) = state
+                    .client
+                    .edit_network(
+                        &network_id,
+                        EditNetwork {
+                            rules: program.rules.clone(),
+                            capabilities: program.network_capabilities(),
+                            tags: program.network_tags(),
+                            rules_source: source.clone(),
+                            ..EditNetwork::from(&*network)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    network.capabilities = program.network_capabilities();
+                    network.tags = program.network_tags();
+                    network.rules = program.rules;
+                    network.rules_source = source;
+                    println!("{}", "✔ Network updated".bright_green());
+                }
+            }
+            10 => {
+                println!("{}", "⏳ Fetching members".yellow());
+                let members = match state.client.members_detailed(&network_id).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        println!("❌ Request failed: {}", e);
+                        return;
+                    }
+                };
+
+                let issues = check(&state.networks[selected_index], &members);
+                if issues.is_empty() {
+                    println!("{}", "✔ No issues found".bright_green());
+                }
+                for issue in issues {
+                    println!(
+                        "{}",
+                        format!("⚠ {}: {}", issue.kind, issue.message).yellow()
+                    );
+                }
+            }
+            11 if Confirm::new()
+                .with_prompt("Are you sure want to delete this network?")
+                .interact()
+                .unwrap() =>
+            {
+                if let Err(e) = state.client.delete_network(&network_id).await {
+                    println!("❌ Request failed: {}", e);
+                    return;
+                }
+
+                println!("{}", "✔ Network deleted".bright_green());
+                state.selected_network = None;
+                state.networks.remove(
+                    state
+                        .networks
+                        .iter()
+                        .position(|x| x.id == network_id)
+                        .unwrap(),
+                );
+            }
+            _ => {}
+        },
+        None => state.selected_network = None,
+    }
+}
+
+async fn members_list(state: &mut State) {
+    let view = state.member_filter.apply(state.members.as_ref().unwrap());
+    let index = {
+        let members = state.members.as_ref().unwrap();
+        let items = view
+            .iter()
+            .map(|i| &members[*i])
+            .map(|x| {
+                let s = format!("{} {}", if !x.authorized { "🔒" } else { "🔓" }, x);
+                let s = if x.authorized {
+                    s.bright_green()
+                } else {
+                    s.bright_red()
+                };
+                match &state.peers {
+                    Some(peers) => {
+                        format!(
+                            "{} {}",
+                            s,
+                            format!("· {}", presence(peers.get(&x.id))).dimmed()
+                        )
+                    }
+                    None => s.to_string(),
+                }
+            })
+            .chain([
+                "Search...".to_string(),
+                "Filter and sort...".to_string(),
+                "Select multiple...".to_string(),
+            ])
+            .collect::<Vec<String>>();
+
+        let network = &state.networks[state.selected_network.unwrap()];
+        Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(if state.member_filter.is_empty() {
+                format!("Members of {}", network)
+            } else {
+                format!("Members of {} ({})", network, state.member_filter)
+            })
+            .items(&items)
+            .default(0)
+            .interact_opt()
+            .unwrap()
+    };
+
+    match index {
+        Some(index) if index < view.len() => member_options(state, view[index]).await,
+        Some(index) if index == view.len() => {
+            state.member_filter.search = prompt_search(state.member_filter.search.as_deref())
+        }
+        Some(index) if index == view.len() + 1 => {
+            if let Some(filter) = edit_filter(&state.member_filter) {
+                state.member_filter = filter;
+            }
+        }
+        Some(_) => bulk_actions(state, &view).await,
+        None => {
+            state.members = None;
+            state.peers = None;
+            state.member_filter = MemberFilter::default();
+        }
+    }
+}
+
+/// Applies one action to several of the listed members at once and reloads the member list.
+async fn bulk_actions(state: &mut State, view: &[usize]) {
+    let network = &state.networks[state.selected_network.unwrap()];
+    let members = state.members.as_ref().unwrap();
+
+    let selected = match MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Members (space to select)")
+        .items(&view.iter().map(|i| &members[*i]).collect::<Vec<_>>())
+        .interact_opt()
+        .unwrap()
+    {
+        Some(selected) if !selected.is_empty() => selected
+            .into_iter()
+            .map(|i| view[i])
+            .collect::<Vec<usize>>(),
+        _ => return,
+    };
+
+    let action = match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{} members selected", selected.len()))
+        .items(&["Authorize", "Deauthorize", "Set tag", "Delete"])
+        .default(0)
+        .interact_opt()
+        .unwrap()
+    {
+        Some(action) => action,
+        None => return,
+    };
+
+    let mut tag = None;
+    match action {
+        2 => {
+            let program = Program::from_network(network);
+            if program.tags.is_empty() {
+                println!(
+                    "{}",
+                    "No tags are defined in the network rules".bright_blue()
+                );
+                return;
+            }
+            let names = program
+                .tags
+                .iter()
+                .map(|x| x.name.clone())
+                .collect::<Vec<String>>();
+            let definition = match Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Tag")
+                .items(&names)
+                .default(0)
+                .interact_opt()
+                .unwrap()
+            {
+                Some(i) => &program.tags[i],
+                None => return,
+            };
+            tag = match prompt_tag_value(definition) {
+                Some(value) => Some((definition.id, value)),
+                None => return,
+            };
+        }
+        3 if !Confirm::new()
+            .with_prompt(format!(
+                "Are you sure want to delete {} members?",
+                selected.len()
+            ))
+            .interact()
+            .unwrap() =>
+        {
+            return;
+        }
+        _ => {}
+    }
+
+    let client = &state.client;
+    let mut pending = selected
+        .iter()
+        .map(|i| {
+            let member = &members[*i];
+            async move {
+                let result = match (action, tag) {
+                    (0 | 1, _) => {
+                        let data = EditMember {
+                            authorized: action == 0,
+                            ..EditMember::from(member)
+                        };
+                        client.edit_member(&member.nwid, &member.id, data).await
+                    }
+                    (2, Some((id, value))) => {
+                        let data = EditMember {
+                            tags: member.tags_with(id, value),
+                            ..EditMember::from(member)
+                        };
+                        client.edit_member(&member.nwid, &member.id, data).await
+                    }
+                    _ => client.delete_member(&member.nwid, &member.id).await,
+                };
+                (member, result)
+            }
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let (total, mut done, mut failed) = (selected.len(), 0, 0);
+    while let Some((member, result)) = pending.next().await {
+        done += 1;
+        match result {
+            Ok(()) => println!("[{}/{}] ✔ {}", done, total, member),
+            Err(e) => {
+                failed += 1;
+                println!("[{}/{}] ❌ {}: {}", done, total, member, e)
+            }
+        }
+    }
+    drop(pending);
+
+    let summary = format!("{} succeeded, {} failed", total - failed, failed);
+    if failed == 0 {
+        println!("{}", format!("✔ {}", summary).bright_green());
+    } else {
+        println!("{}", format!("⚠ {}", summary).yellow());
+    }
+
+    let network_id = network.id.clone();
+    match state.client.members_detailed(&network_id).await {
+        Ok(members) if members.is_empty() => state.members = None,
+        Ok(members) => state.members = Some(members),
+        Err(e) => {
+            println!("❌ Request failed: {}", e);
+            state.members = None;
+        }
+    }
+}
+
+async fn member_options(state: &mut State, index: usize) {
+    let members = state.members.as_mut().unwrap();
+    let used = RefCell::new(used_ips(members.iter()));
+    let member = &mut members[index];
+    let network = &state.networks[state.selected_network.unwrap()];
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Member {} of {}", member, network))
+        .items(&[
+            "Info",
+            if member.authorized {
+                "Deauthorize"
+            } else {
+                "Authorize"
+            },
+            "Set name",
+            "IP assignments",
+            "Tags",
+            "Capabilities",
+            if member.active_bridge {
+                "Disable active bridge"
+            } else {
+                "Enable active bridge"
+            },
+            if member.no_auto_assign_ips {
+                "Enable IP auto-assignment"
+            } else {
+                "Disable IP auto-assignment"
+            },
+            if member.sso_exempt {
+                "Disable SSO exemption"
+            } else {
+                "Enable SSO exemption"
+            },
+            "Delete",
+        ])
+        .default(0)
+        .interact_opt()
+        .unwrap();
+
+    if let Some(i) = selection {
+        match i {
+            0 => {
+                println!("\n{}", member.details());
+                if let Some(peers) = &state.peers {
+                    println!("{}", presence_details(peers.get(&member.id)));
+                }
+            }
+            1 => {
+                if let Err(e) = state
+                    .client
+                    .edit_member(
+                        &member.nwid,
+                        &member.id,
+                        EditMember {
+                            authorized: !member.authorized,
+                            ..EditMember::from(&*member)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.authorized = !member.authorized;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            2 => {
+                let name: String = match Input::new().with_prompt("New name").interact_text() {
+                    Ok(e) => e,
+                    Err(_) => {
+                        return;
+                    }
+                };
+
+                let name = if name.is_empty() { None } else { Some(name) };
+
+                if let Err(e) = state
+                    .client
+                    .edit_member(
+                        &member.nwid,
+                        &member.id,
+                        EditMember {
+                            name: name.clone(),
+                            ..EditMember::from(&*member)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.name = name;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            3 => {
+                let ips = match edit_list("IP assignments", member.ip_assignments.clone(), |x| {
+                    let free = next_free(&network.ip_assignment_pools, &used.borrow());
+                    let ip = prompt_ip(x, &network.routes, free)?;
+                    used.borrow_mut().extend(ip.parse::<IpAddr>());
+                    Some(ip)
+                }) {
+                    Some(ips) => ips,
+                    None => return,
+                };
+
+                if let Err(e) = state
+                    .client
+                    .edit_member(
+                        &member.nwid,
+                        &member.id,
+                        EditMember {
+                            ip_assignments: ips.clone(),
+                            ..EditMember::from(&*member)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.ip_assignments = ips;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            4 => {
+                let program = Program::from_network(network);
+                if program.tags.is_empty() {
+                    println!(
+                        "{}",
+                        "No tags are defined in the network rules".bright_blue()
+                    );
+                    return;
+                }
+
+                let items = program
+                    .tags
+                    .iter()
+                    .map(|tag| match member.tags.iter().find(|x| x.0 == tag.id) {
+                        Some(x) => program.describe_tag(x),
+                        None => format!("{} (not set)", tag.name),
+                    })
+                    .collect::<Vec<String>>();
+                let tag = match Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Tags")
+                    .items(&items)
+                    .default(0)
+                    .interact_opt()
+                    .unwrap()
+                {
+                    Some(i) => &program.tags[i],
+                    None => return,
+                };
+
+                let value = match prompt_tag_value(tag) {
+                    Some(value) => value,
+                    None => return,
+                };
+                let tags = member.tags_with(tag.id, value);
+
+                if let Err(e) = state
+                    .client
+                    .edit_member(
+                        &member.nwid,
+                        &member.id,
+                        EditMember {
+                            tags: tags.clone(),
+                            ..EditMember::from(&*member)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.tags = tags;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            5 => {
+                let program = Program::from_network(network);
+                if program.capabilities.is_empty() {
+                    println!(
+                        "{}",
+                        "No capabilities are defined in the network rules".bright_blue()
+                    );
+                    return;
+                }
+
+                let names = program
+                    .capabilities
+                    .iter()
+                    .map(|x| x.name.clone())
+                    .collect::<Vec<String>>();
+                let checked = program
+                    .capabilities
+                    .iter()
+                    .map(|x| member.capabilities.contains(&x.id))
+                    .collect::<Vec<bool>>();
+                let selected = match MultiSelect::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Capabilities (space to toggle)")
+                    .items(&names)
+                    .defaults(&checked)
+                    .interact_opt()
+                    .unwrap()
+                {
+                    Some(selected) => selected,
+                    None => return,
+                };
+
+                // Capabilities the rules do not define anymore are kept as is
+                let capabilities = member
+                    .capabilities
+                    .iter()
+                    .copied()
+                    .filter(|id| !program.capabilities.iter().any(|x| x.id == *id))
+                    .chain(selected.iter().map(|i| program.capabilities[*i].id))
+                    .collect::<Vec<u32>>();
+
+                if let Err(e) = state
+                    .client
+                    .edit_member(
+                        &member.nwid,
+                        &member.id,
+                        EditMember {
+                            capabilities: capabilities.clone(),
+                            ..EditMember::from(&*member)
+                        },
+                    )
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.capabilities = capabilities;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            6..=8 => {
+                let mut data = EditMember::from(&*member);
+                match i {
+                    6 => data.active_bridge = !data.active_bridge,
+                    7 => data.no_auto_assign_ips = !data.no_auto_assign_ips,
+                    _ => data.sso_exempt = !data.sso_exempt,
+                }
+                let (active_bridge, no_auto_assign_ips, sso_exempt) =
+                    (data.active_bridge, data.no_auto_assign_ips, data.sso_exempt);
+
+                if let Err(e) = state
+                    .client
+                    .edit_member(&member.nwid, &member.id, data)
+                    .await
+                {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    member.active_bridge = active_bridge;
+                    member.no_auto_assign_ips = no_auto_assign_ips;
+                    member.sso_exempt = sso_exempt;
+                    println!("{}", "✔ Member updated".bright_green());
+                }
+            }
+            9 => {
+                if let Err(e) = state.client.delete_member(&member.nwid, &member.id).await {
+                    println!("❌ Request failed: {}", e)
+                } else {
+                    members.remove(index);
+                    println!("{}", "✔ Member deleted".bright_green());
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::dto::{
+    MemberResponse, NetworkIPAssignmentPool, NetworkResponse, NetworkRoute, StatusResponse,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Plain,
+    /// Aligned columns
+    Table,
+    Json,
+    Yaml,
+}
+
+/// Types that can be rendered as a row of a table.
+pub trait Tabular {
+    fn headers() -> Vec<&'static str>;
+    fn row(&self) -> Vec<String>;
+}
+
+impl Tabular for StatusResponse {
+    fn headers() -> Vec<&'static str> {
+        vec!["NODE ID", "ONLINE", "VERSION"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.address.clone(),
+            self.online.to_string(),
+            format!(
+                "{}.{}.{}",
+                self.version_major, self.version_minor, self.version_rev
+            ),
+        ]
+    }
+}
+
+impl Tabular for NetworkResponse {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "ID", "NAME", "PRIVATE", "V4 ZT", "V6 MODES", "ROUTES", "POOLS",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.name.clone().unwrap_or_default(),
+            self.private.to_string(),
+            self.v4_assign_mode.zt.to_string(),
+            self.v6_assign_mode.to_string(),
+            self.routes
+                .iter()
+                .map(|x| x.target.clone())
+                .collect::<Vec<String>>()
+                .join(","),
+            self.ip_assignment_pools
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ]
+    }
+}
+
+impl Tabular for MemberResponse {
+    fn headers() -> Vec<&'static str> {
+        vec!["ID", "NAME", "AUTHORIZED", "IPS"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.name.clone().unwrap_or_default(),
+            self.authorized.to_string(),
+            self.ip_assignments.join(","),
+        ]
+    }
+}
+
+impl Tabular for NetworkRoute {
+    fn headers() -> Vec<&'static str> {
+        vec!["TARGET", "VIA"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.target.clone(), self.via.clone().unwrap_or_default()]
+    }
+}
+
+impl Tabular for NetworkIPAssignmentPool {
+    fn headers() -> Vec<&'static str> {
+        vec!["START", "END"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.ip_range_start.clone(), self.ip_range_end.clone()]
+    }
+}
+
+/// Renders rows as columns padded to the widest cell.
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths = headers
+        .iter()
+        .map(|x| x.chars().count())
+        .collect::<Vec<usize>>();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![line(headers.to_vec())];
+    lines.extend(
+        rows.iter()
+            .map(|row| line(row.iter().map(|x| x.as_str()).collect())),
+    );
+    lines.join("\n")
+}
+
+fn serialize<T: Serialize + ?Sized>(format: OutputFormat, value: &T) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(value).unwrap(),
+        OutputFormat::Yaml => serde_yaml::to_string(value).unwrap().trim_end().to_string(),
+        _ => unreachable!(),
+    }
+}
+
+/// Prints a listing, `line` renders a single item in plain mode.
+pub fn print_list<T: Serialize + Tabular>(
+    format: OutputFormat,
+    items: &[T],
+    line: impl Fn(&T) -> String,
+) {
+    match format {
+        OutputFormat::Plain => {
+            for item in items {
+                println!("{}", line(item));
+            }
+        }
+        OutputFormat::Table => {
+            let rows = items.iter().map(|x| x.row()).collect::<Vec<Vec<String>>>();
+            println!("{}", table(&T::headers(), &rows));
+        }
+        OutputFormat::Json | OutputFormat::Yaml => println!("{}", serialize(format, items)),
+    }
+}
+
+/// Prints a single item, tables are rendered as key/value pairs.
+pub fn print_item<T: Serialize + Tabular>(
+    format: OutputFormat,
+    item: &T,
+    details: impl FnOnce(&T) -> String,
+) {
+    match format {
+        OutputFormat::Plain => println!("{}", details(item)),
+        OutputFormat::Table => {
+            let rows = T::headers()
+                .into_iter()
+                .zip(item.row())
+                .map(|(header, value)| vec![header.to_string(), value])
+                .collect::<Vec<Vec<String>>>();
+            println!("{}", table(&["FIELD", "VALUE"], &rows));
+        }
+        OutputFormat::Json | OutputFormat::Yaml => println!("{}", serialize(format, item)),
+    }
+}
+
+/// Prints a value without a table form, `table` falls back to `plain`.
+pub fn print_value<T: Serialize + ?Sized>(format: OutputFormat, value: &T, plain: String) {
+    match format {
+        OutputFormat::Plain | OutputFormat::Table => println!("{}", plain),
+        OutputFormat::Json | OutputFormat::Yaml => println!("{}", serialize(format, value)),
+    }
+}
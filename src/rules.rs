@@ -0,0 +1,199 @@
+use ipnet::{Ipv4Net, Ipv6Net};
+use serde_json::Value;
+
+/// Type of a field of a flow rule as rendered by the controller.
+#[derive(Clone, Copy)]
+enum Field {
+    Int,
+    /// Integer or `null`
+    OptionalInt,
+    /// Integer or hex string, e.g. `"0x1000"`
+    Mask,
+    Bool,
+    /// 10 digit hex ZeroTier address
+    Address,
+    Mac,
+    Ipv4,
+    Ipv6,
+}
+
+/// Known rule types with their required fields.
+const RULE_TYPES: &[(&str, &[(&str, Field)])] = &[
+    ("ACTION_DROP", &[]),
+    ("ACTION_ACCEPT", &[]),
+    ("ACTION_BREAK", &[]),
+    ("ACTION_DEBUG_LOG", &[]),
+    (
+        "ACTION_TEE",
+        &[
+            ("address", Field::Address),
+            ("flags", Field::Int),
+            ("length", Field::Int),
+        ],
+    ),
+    (
+        "ACTION_WATCH",
+        &[
+            ("address", Field::Address),
+            ("flags", Field::Int),
+            ("length", Field::Int),
+        ],
+    ),
+    (
+        "ACTION_REDIRECT",
+        &[("address", Field::Address), ("flags", Field::Int)],
+    ),
+    ("MATCH_SOURCE_ZEROTIER_ADDRESS", &[("zt", Field::Address)]),
+    ("MATCH_DEST_ZEROTIER_ADDRESS", &[("zt", Field::Address)]),
+    ("MATCH_VLAN_ID", &[("vlanId", Field::Int)]),
+    ("MATCH_VLAN_PCP", &[("vlanPcp", Field::Int)]),
+    ("MATCH_VLAN_DEI", &[("vlanDei", Field::Int)]),
+    ("MATCH_MAC_SOURCE", &[("mac", Field::Mac)]),
+    ("MATCH_MAC_DEST", &[("mac", Field::Mac)]),
+    ("MATCH_IPV4_SOURCE", &[("ip", Field::Ipv4)]),
+    ("MATCH_IPV4_DEST", &[("ip", Field::Ipv4)]),
+    ("MATCH_IPV6_SOURCE", &[("ip", Field::Ipv6)]),
+    ("MATCH_IPV6_DEST", &[("ip", Field::Ipv6)]),
+    (
+        "MATCH_IP_TOS",
+        &[
+            ("mask", Field::Int),
+            ("start", Field::Int),
+            ("end", Field::Int),
+        ],
+    ),
+    ("MATCH_IP_PROTOCOL", &[("ipProtocol", Field::Int)]),
+    ("MATCH_ETHERTYPE", &[("etherType", Field::Int)]),
+    (
+        "MATCH_ICMP",
+        &[("icmpType", Field::Int), ("icmpCode", Field::OptionalInt)],
+    ),
+    (
+        "MATCH_IP_SOURCE_PORT_RANGE",
+        &[("start", Field::Int), ("end", Field::Int)],
+    ),
+    (
+        "MATCH_IP_DEST_PORT_RANGE",
+        &[("start", Field::Int), ("end", Field::Int)],
+    ),
+    ("MATCH_CHARACTERISTICS", &[("mask", Field::Mask)]),
+    (
+        "MATCH_FRAME_SIZE_RANGE",
+        &[("start", Field::Int), ("end", Field::Int)],
+    ),
+    ("MATCH_RANDOM", &[("probability", Field::Int)]),
+    (
+        "MATCH_TAGS_DIFFERENCE",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAGS_BITWISE_AND",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAGS_BITWISE_OR",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAGS_BITWISE_XOR",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAGS_EQUAL",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAG_SENDER",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_TAG_RECEIVER",
+        &[("id", Field::Int), ("value", Field::Int)],
+    ),
+    (
+        "MATCH_INTEGER_RANGE",
+        &[
+            ("start", Field::Mask),
+            ("end", Field::Mask),
+            ("idx", Field::Int),
+            ("little", Field::Bool),
+            ("bits", Field::Int),
+        ],
+    ),
+];
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn check_field(value: &Value, field: Field) -> bool {
+    match field {
+        Field::Int => value.is_u64(),
+        Field::OptionalInt => value.is_u64() || value.is_null(),
+        Field::Mask => {
+            value.is_u64()
+                || value
+                    .as_str()
+                    .is_some_and(|x| u64::from_str_radix(x.trim_start_matches("0x"), 16).is_ok())
+        }
+        Field::Bool => value.is_boolean(),
+        Field::Address => value.as_str().is_some_and(|x| is_hex(x, 10)),
+        Field::Mac => value
+            .as_str()
+            .is_some_and(|x| is_hex(&x.replace(':', ""), 12)),
+        Field::Ipv4 => value.as_str().is_some_and(|x| x.parse::<Ipv4Net>().is_ok()),
+        Field::Ipv6 => value.as_str().is_some_and(|x| x.parse::<Ipv6Net>().is_ok()),
+    }
+}
+
+/// Checks that every rule is an object of a known type carrying its required fields.
+pub fn validate(rules: &[Value]) -> Result<(), String> {
+    for (i, rule) in rules.iter().enumerate() {
+        let position = format!("rule #{}", i + 1);
+        let object = rule
+            .as_object()
+            .ok_or_else(|| format!("{}: expected an object", position))?;
+        let kind = object
+            .get("type")
+            .and_then(|x| x.as_str())
+            .ok_or_else(|| format!("{}: missing `type`", position))?;
+        let fields = RULE_TYPES
+            .iter()
+            .find(|x| x.0 == kind)
+            .map(|x| x.1)
+            .ok_or_else(|| format!("{}: unknown rule type `{}`", position, kind))?;
+
+        for flag in ["not", "or"] {
+            if object.get(flag).is_some_and(|x| !x.is_boolean()) {
+                return Err(format!(
+                    "{} ({}): `{}` must be a boolean",
+                    position, kind, flag
+                ));
+            }
+        }
+        for (name, field) in fields {
+            match object.get(*name) {
+                Some(value) if check_field(value, *field) => {}
+                Some(value) => {
+                    return Err(format!(
+                        "{} ({}): invalid value {} for `{}`",
+                        position, kind, value, name
+                    ));
+                }
+                None => {
+                    return Err(format!("{} ({}): missing `{}`", position, kind, name));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses and validates a JSON array of rules.
+pub fn parse(text: &str) -> Result<Vec<Value>, String> {
+    let rules =
+        serde_json::from_str::<Vec<Value>>(text).map_err(|e| format!("invalid JSON: {}", e))?;
+    validate(&rules)?;
+    Ok(rules)
+}
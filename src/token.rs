@@ -0,0 +1,81 @@
+use reqwest::Url;
+use reqwest::header::HeaderValue;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Places where the ZeroTier service and `zerotier-cli` keep the local API token.
+fn candidates() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".zeroTierOneAuthToken"));
+    }
+    paths.push(PathBuf::from("/var/lib/zerotier-one/authtoken.secret"));
+    paths.push(PathBuf::from("/var/snap/zerotier/common/authtoken.secret"));
+    paths
+}
+
+/// Whether the controller runs on this machine, so its token file can be looked up.
+pub fn is_local(url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    match url.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_matches(['[', ']'])
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+/// Reads a token file, explaining how to fix missing permissions.
+pub fn read(path: &Path) -> Result<String, Box<dyn Error>> {
+    let token = fs::read_to_string(path).map_err(|e| match e.kind() {
+        ErrorKind::PermissionDenied => format!(
+            "Cannot read token file {}: permission denied. It is usually readable by root only, \
+             run with sudo or copy it to ~/.zeroTierOneAuthToken with `chmod 600`",
+            path.display()
+        ),
+        _ => format!("Cannot read token file {}: {}", path.display(), e),
+    })?;
+    let token = token.trim().to_string();
+
+    if token.is_empty() || HeaderValue::from_str(&token).is_err() {
+        return Err(format!(
+            "Token file {} does not contain a valid token",
+            path.display()
+        )
+        .into());
+    }
+    Ok(token)
+}
+
+/// Reads the first existing local token file.
+pub fn discover() -> Result<String, Box<dyn Error>> {
+    let mut error = None;
+    for path in candidates().iter().filter(|x| x.exists()) {
+        match read(path) {
+            Ok(token) => return Ok(token),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    Err(error.unwrap_or_else(|| {
+        format!(
+            "No API token found. Set TOKEN, pass --token-file or make one of {} readable",
+            candidates()
+                .iter()
+                .map(|x| x.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .into()
+    }))
+}
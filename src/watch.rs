@@ -0,0 +1,222 @@
+use crate::api::APIClient;
+use crate::dto::MemberResponse;
+use crate::error::Error;
+use chrono::Local;
+use futures::future::try_join_all;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A new member that waits for authorization
+    JoinRequest,
+    /// A new member that is already authorized
+    Added,
+    Authorized,
+    Deauthorized,
+    IpsChanged,
+    Deleted,
+}
+
+/// A member change seen between two polls.
+#[derive(Serialize, Debug)]
+pub struct Event {
+    /// RFC 3339 time of the poll that saw the change
+    pub time: String,
+    pub event: EventKind,
+    pub network: String,
+    pub network_name: Option<String>,
+    pub member: String,
+    pub name: Option<String>,
+    pub ips: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_ips: Option<Vec<String>>,
+}
+
+fn format_ips(ips: &[String]) -> String {
+    if ips.is_empty() {
+        "No IPs".to_string()
+    } else {
+        ips.join(", ")
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let member = match &self.name {
+            Some(name) => format!("{} ({})", name, self.member),
+            None => self.member.clone(),
+        };
+        let network = match &self.network_name {
+            Some(name) => format!("{} ({})", name, self.network),
+            None => self.network.clone(),
+        };
+        match self.event {
+            EventKind::JoinRequest => write!(f, "⚡ {} wants to join {}", member, network),
+            EventKind::Added => write!(f, "➕ {} was added to {}", member, network),
+            EventKind::Authorized => write!(f, "🔓 {} was authorized on {}", member, network),
+            EventKind::Deauthorized => {
+                write!(f, "🔒 {} was deauthorized on {}", member, network)
+            }
+            EventKind::IpsChanged => write!(
+                f,
+                "🔀 IPs of {} on {} changed: {} → {}",
+                member,
+                network,
+                format_ips(self.previous_ips.as_deref().unwrap_or_default()),
+                format_ips(&self.ips)
+            ),
+            EventKind::Deleted => write!(f, "🗑  {} was removed from {}", member, network),
+        }
+    }
+}
+
+struct WatchedNetwork {
+    name: Option<String>,
+    members: BTreeMap<String, MemberResponse>,
+}
+
+/// Members of the watched networks as of the last poll.
+pub struct Watcher {
+    /// Networks given on the command line, all networks of the controller when empty
+    only: Vec<String>,
+    networks: BTreeMap<String, WatchedNetwork>,
+    started: bool,
+}
+
+impl Watcher {
+    /// Takes the initial state, changes are reported from the next poll on.
+    pub async fn new(client: &APIClient, only: Vec<String>) -> Result<Self, Error> {
+        let mut watcher = Self {
+            only,
+            networks: BTreeMap::new(),
+            started: false,
+        };
+        watcher.poll(client).await?;
+        watcher.started = true;
+        Ok(watcher)
+    }
+
+    pub fn network_count(&self) -> usize {
+        self.networks.len()
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = &MemberResponse> {
+        self.networks.values().flat_map(|x| x.members.values())
+    }
+
+    /// Fetches members whose revision changed and returns what happened since the last poll.
+    ///
+    /// Everything is fetched before the state is updated, so a failed poll loses no events.
+    pub async fn poll(&mut self, client: &APIClient) -> Result<Vec<Event>, Error> {
+        let ids = if self.only.is_empty() {
+            client.networks().await?
+        } else {
+            self.only.clone()
+        };
+
+        let mut fetched = Vec::new();
+        for nwid in &ids {
+            let name = match self.networks.get(nwid) {
+                Some(network) => network.name.clone(),
+                None => client.network(nwid).await?.name.filter(|x| !x.is_empty()),
+            };
+            let members = self.networks.get(nwid).map(|x| &x.members);
+            let revisions = client.members(nwid).await?;
+            let changed = revisions
+                .iter()
+                .filter(|(id, revision)| {
+                    members
+                        .and_then(|x| x.get(*id))
+                        .is_none_or(|x| x.revision != **revision)
+                })
+                .map(|(id, _)| client.member(nwid, id));
+            let mut changed = try_join_all(changed).await?;
+            changed.sort_by(|a, b| a.id.cmp(&b.id));
+            fetched.push((nwid, name, revisions, changed));
+        }
+
+        let time = Local::now().to_rfc3339();
+        let mut events = Vec::new();
+
+        for (nwid, name, revisions, changed) in fetched {
+            let network = self.networks.entry(nwid.clone()).or_insert(WatchedNetwork {
+                name,
+                members: BTreeMap::new(),
+            });
+
+            let event = |event, member: &MemberResponse, previous_ips| Event {
+                time: time.clone(),
+                event,
+                network: nwid.clone(),
+                network_name: network.name.clone(),
+                member: member.id.clone(),
+                name: member.name.clone().filter(|x| !x.is_empty()),
+                ips: member.ip_assignments.clone(),
+                previous_ips,
+            };
+            for member in &changed {
+                match network.members.get(&member.id) {
+                    None if !self.started => {}
+                    None if member.authorized => events.push(event(EventKind::Added, member, None)),
+                    None => events.push(event(EventKind::JoinRequest, member, None)),
+                    Some(old) => {
+                        match (old.authorized, member.authorized) {
+                            (false, true) => {
+                                events.push(event(EventKind::Authorized, member, None))
+                            }
+                            (true, false) => {
+                                events.push(event(EventKind::Deauthorized, member, None))
+                            }
+                            _ => {}
+                        }
+                        if old.ip_assignments != member.ip_assignments {
+                            events.push(event(
+                                EventKind::IpsChanged,
+                                member,
+                                Some(old.ip_assignments.clone()),
+                            ));
+                        }
+                    }
+                }
+            }
+            events.extend(
+                network
+                    .members
+                    .values()
+                    .filter(|x| !revisions.contains_key(&x.id))
+                    .map(|x| event(EventKind::Deleted, x, None)),
+            );
+
+            network.members.retain(|id, _| revisions.contains_key(id));
+            network
+                .members
+                .extend(changed.into_iter().map(|x| (x.id.clone(), x)));
+        }
+
+        // Members of deleted networks are gone as well
+        let removed = self
+            .networks
+            .keys()
+            .filter(|x| !ids.contains(x))
+            .cloned()
+            .collect::<Vec<String>>();
+        for nwid in removed {
+            let network = self.networks.remove(&nwid).unwrap();
+            events.extend(network.members.into_values().map(|member| Event {
+                time: time.clone(),
+                event: EventKind::Deleted,
+                network: nwid.clone(),
+                network_name: network.name.clone(),
+                member: member.id,
+                name: member.name.filter(|x| !x.is_empty()),
+                ips: member.ip_assignments,
+                previous_ips: None,
+            }));
+        }
+
+        Ok(events)
+    }
+}